@@ -0,0 +1,114 @@
+use std::{error::Error, fs, path::PathBuf, process::ExitCode};
+
+use clap::{Parser, ValueEnum};
+use cli::SolverArgs;
+use solver::{
+    scenario::ScenarioConfig,
+    tuning::{self, TuningStats},
+};
+
+/// Solves generated test cases and prints score statistics, for parameter tuning.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Number of test cases to solve
+    #[arg(long, default_value_t = 100)]
+    cases: usize,
+
+    /// Seed for generating the test cases and the per-case solver seeds
+    #[arg(long, default_value_t = 123)]
+    seed: u64,
+
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
+    /// Scenario JSON describing the generated test cases (the default 6x5 full room when omitted)
+    #[arg(long)]
+    scenario: Option<PathBuf>,
+
+    /// Number of seat rows, overriding the scenario
+    #[arg(long)]
+    depth: Option<usize>,
+
+    /// Number of seat columns, overriding the scenario
+    #[arg(long)]
+    width: Option<usize>,
+
+    /// Ratio of vacant seats, overriding the scenario
+    #[arg(long)]
+    vacancy_ratio: Option<f64>,
+
+    #[command(flatten)]
+    solver: SolverArgs,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+}
+
+fn scenario(args: &Args) -> Result<ScenarioConfig, Box<dyn Error>> {
+    let mut scenario = match &args.scenario {
+        Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+        None => ScenarioConfig::default(),
+    };
+
+    if let Some(depth) = args.depth {
+        scenario.depth = depth;
+    }
+    if let Some(width) = args.width {
+        scenario.width = width;
+    }
+    if let Some(vacancy_ratio) = args.vacancy_ratio {
+        scenario.vacancy_ratio = vacancy_ratio;
+    }
+
+    Ok(scenario)
+}
+
+fn print_stats(stats: &TuningStats, format: Format) -> Result<(), serde_json::Error> {
+    match format {
+        Format::Json => println!("{}", serde_json::to_string(stats)?),
+        Format::Csv => {
+            let mut header = "case_cnt,mean,sigma,individual_mean,runtime_ms".to_string();
+            let mut values = format!(
+                "{},{},{},{},{}",
+                stats.case_cnt, stats.mean, stats.sigma, stats.individual_mean, stats.runtime_ms
+            );
+            for (kind, stat) in stats.moves.iter() {
+                header += &format!(",{:?}_proposed,{:?}_accepted", kind, kind);
+                values += &format!(",{},{}", stat.proposed, stat.accepted);
+            }
+
+            println!("{}", header);
+            println!("{}", values);
+        }
+    }
+
+    Ok(())
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let stats = tuning::run(
+        &args.solver.config(),
+        &scenario(args)?,
+        args.cases,
+        args.seed,
+    )?;
+    print_stats(&stats, args.format)?;
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
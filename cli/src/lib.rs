@@ -0,0 +1,212 @@
+use clap::{Args, ValueEnum};
+use solver::structs::{
+    Algorithm, Config, MoveWeights, Objective, TemperatureSchedule, TestVersions, Weights,
+};
+
+/// Solver options shared by the command-line tools.
+#[derive(Args, Debug)]
+pub struct SolverArgs {
+    #[arg(long, value_enum, default_value_t = AlgorithmArg::SimulatedAnnealing)]
+    pub algorithm: AlgorithmArg,
+
+    /// Time budget in milliseconds
+    #[arg(long)]
+    pub time_limit: Option<u64>,
+
+    /// Number of iterations when no time budget is given
+    #[arg(long)]
+    pub loop_cnt: Option<usize>,
+
+    /// Start temperature of simulated annealing
+    #[arg(long)]
+    pub temperature1: Option<f64>,
+
+    /// End temperature of simulated annealing
+    #[arg(long)]
+    pub temperature2: Option<f64>,
+
+    /// Choose the temperatures from sampled score changes instead
+    #[arg(long)]
+    pub auto_temperature: bool,
+
+    #[arg(long, value_enum, default_value_t = ScheduleArg::Linear)]
+    pub schedule: ScheduleArg,
+
+    /// Number of cooling cycles of the reheating schedule
+    #[arg(long, default_value_t = 3)]
+    pub reheat_cycles: usize,
+
+    /// Number of layouts kept at each step of beam search
+    #[arg(long)]
+    pub beam_width: Option<usize>,
+
+    /// Frequency of a simulated annealing move as NAME=VALUE, e.g. `cycle3=0.5` (repeatable).
+    /// NAME is one of swap, cycle3, shift, neighbor_swap, worst_swap
+    #[arg(long = "move", value_parser = parse_move_weight)]
+    pub moves: Vec<(String, f64)>,
+
+    /// Score weight as NAME=VALUE, e.g. `academic=2000` (repeatable). NAME is one of
+    /// prev_adj_distance, blackboard_distance, academic, exercise, leadership, gender,
+    /// same_version, zone_repeat
+    #[arg(long = "weight", value_parser = parse_weight)]
+    pub weights: Vec<(String, f64)>,
+
+    /// Seat an exam: separate students from their previous neighbours (simulated annealing only)
+    #[arg(long)]
+    pub exam: bool,
+
+    /// Versions of the exam, kept apart from each other and printed on the PDF
+    #[arg(long, value_enum, default_value_t = VersionsArg::Single, requires = "exam")]
+    pub versions: VersionsArg,
+}
+
+impl SolverArgs {
+    pub fn config(&self) -> Config {
+        let mut config = Config {
+            algorithm: self.algorithm.into(),
+            time_limit_ms: self.time_limit,
+            auto_temperature: self.auto_temperature,
+            schedule: match self.schedule {
+                ScheduleArg::Linear => TemperatureSchedule::Linear,
+                ScheduleArg::Exponential => TemperatureSchedule::Exponential,
+                ScheduleArg::Reheating => TemperatureSchedule::Reheating {
+                    cycles: self.reheat_cycles,
+                },
+            },
+            objective: if self.exam {
+                Objective::Exam {
+                    versions: self.versions.into(),
+                }
+            } else {
+                Objective::Regular
+            },
+            ..Default::default()
+        };
+
+        if let Some(loop_cnt) = self.loop_cnt {
+            config.loop_cnt = loop_cnt;
+        }
+        if let Some(temperature1) = self.temperature1 {
+            config.temperature1 = temperature1;
+        }
+        if let Some(temperature2) = self.temperature2 {
+            config.temperature2 = temperature2;
+        }
+        if let Some(beam_width) = self.beam_width {
+            config.beam_width = beam_width;
+        }
+
+        for (name, value) in self.moves.iter() {
+            if let Some(weight) = move_weight_mut(&mut config.moves, name) {
+                *weight = *value;
+            }
+        }
+
+        for (name, value) in self.weights.iter() {
+            if let Some(weight) = weight_mut(&mut config.weights, name) {
+                *weight = *value;
+            }
+        }
+
+        config
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum AlgorithmArg {
+    SimulatedAnnealing,
+    BeamSearch,
+    TabuSearch,
+    BranchAndBound,
+}
+
+impl From<AlgorithmArg> for Algorithm {
+    fn from(arg: AlgorithmArg) -> Self {
+        match arg {
+            AlgorithmArg::SimulatedAnnealing => Algorithm::SimulatedAnnealing,
+            AlgorithmArg::BeamSearch => Algorithm::BeamSearch,
+            AlgorithmArg::TabuSearch => Algorithm::TabuSearch,
+            AlgorithmArg::BranchAndBound => Algorithm::BranchAndBound,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum ScheduleArg {
+    Linear,
+    Exponential,
+    Reheating,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum VersionsArg {
+    Single,
+    Checkerboard,
+    AttendanceOrder,
+}
+
+impl From<VersionsArg> for TestVersions {
+    fn from(arg: VersionsArg) -> Self {
+        match arg {
+            VersionsArg::Single => TestVersions::Single,
+            VersionsArg::Checkerboard => TestVersions::Checkerboard,
+            VersionsArg::AttendanceOrder => TestVersions::AttendanceOrder,
+        }
+    }
+}
+
+fn parse_name_value(s: &str) -> Result<(String, f64), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected NAME=VALUE, got `{}`", s))?;
+    let value = value
+        .parse::<f64>()
+        .map_err(|e| format!("invalid value `{}`: {}", value, e))?;
+
+    Ok((name.to_string(), value))
+}
+
+fn parse_weight(s: &str) -> Result<(String, f64), String> {
+    let (name, value) = parse_name_value(s)?;
+
+    if weight_mut(&mut Weights::default(), &name).is_none() {
+        return Err(format!("unknown weight `{}`", name));
+    }
+
+    Ok((name, value))
+}
+
+fn parse_move_weight(s: &str) -> Result<(String, f64), String> {
+    let (name, value) = parse_name_value(s)?;
+
+    if move_weight_mut(&mut MoveWeights::default(), &name).is_none() {
+        return Err(format!("unknown move `{}`", name));
+    }
+
+    Ok((name, value))
+}
+
+fn weight_mut<'a>(weights: &'a mut Weights, name: &str) -> Option<&'a mut f64> {
+    match name {
+        "prev_adj_distance" => Some(&mut weights.prev_adj_distance),
+        "blackboard_distance" => Some(&mut weights.blackboard_distance),
+        "academic" => Some(&mut weights.academic),
+        "exercise" => Some(&mut weights.exercise),
+        "leadership" => Some(&mut weights.leadership),
+        "gender" => Some(&mut weights.gender),
+        "same_version" => Some(&mut weights.same_version),
+        "zone_repeat" => Some(&mut weights.zone_repeat),
+        _ => None,
+    }
+}
+
+fn move_weight_mut<'a>(weights: &'a mut MoveWeights, name: &str) -> Option<&'a mut f64> {
+    match name {
+        "swap" => Some(&mut weights.swap),
+        "cycle3" => Some(&mut weights.cycle3),
+        "shift" => Some(&mut weights.shift),
+        "neighbor_swap" => Some(&mut weights.neighbor_swap),
+        "worst_swap" => Some(&mut weights.worst_swap),
+        _ => None,
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, Read, Write},
+    path::PathBuf,
+    process::ExitCode,
+};
+
+use clap::Parser;
+use cli::SolverArgs;
+#[cfg(feature = "pdf")]
+use solver::structs::{Objective, TestVersions};
+use solver::{
+    batch::ClassInput,
+    structs::{Config, Layout},
+};
+
+/// Generates a new seat layout from the current one.
+///
+/// The input is the same JSON the desktop app sends to the solver: a list of rows, each
+/// holding a student object or `null` for a vacant seat. With `--batch` it is a list of
+/// classes, each with a `name` and a `layout`, and the output is a report of all classes.
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Layout JSON to read, or `-` for stdin
+    input: PathBuf,
+
+    /// Where to write the new layout JSON (stdout when omitted)
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Also render the new layout as a PDF file
+    #[cfg(feature = "pdf")]
+    #[arg(long)]
+    pdf: Option<PathBuf>,
+
+    /// Seed of the random number generator (derived from the input when omitted)
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// JSON list of hard constraints (pinned seats, separations, zones) on the new layout
+    #[arg(long)]
+    constraints: Option<PathBuf>,
+
+    /// Solve several classes in parallel; the PDF gets one page per class
+    #[arg(long)]
+    batch: bool,
+
+    #[command(flatten)]
+    solver: SolverArgs,
+}
+
+fn read_input<T: serde::de::DeserializeOwned>(input: &PathBuf) -> Result<T, Box<dyn Error>> {
+    let json = if input.as_os_str() == "-" {
+        let mut buf = String::new();
+        io::stdin().read_to_string(&mut buf)?;
+        buf
+    } else {
+        fs::read_to_string(input)?
+    };
+
+    Ok(serde_json::from_str(&json)?)
+}
+
+fn write_output(args: &Args, json: &str) -> Result<(), Box<dyn Error>> {
+    match &args.output {
+        Some(path) => fs::write(path, json)?,
+        None => writeln!(io::stdout(), "{}", json)?,
+    }
+
+    Ok(())
+}
+
+/// Writes one page per layout, with the test version of each student for exams.
+#[cfg(feature = "pdf")]
+fn write_pdf(
+    path: &PathBuf,
+    layouts: &[&Layout],
+    objective: Objective,
+) -> Result<(), Box<dyn Error>> {
+    let versions = match objective {
+        Objective::Regular => TestVersions::Single,
+        Objective::Exam { versions } => versions,
+    };
+    let pages = layouts
+        .iter()
+        .map(|layout| solver::export::seat_labels(layout, versions))
+        .collect::<Vec<Vec<Vec<String>>>>();
+
+    let bytes = pdf_generator::gen_pages(pages).map_err(|e| format!("{:?}", e))?;
+    fs::write(path, bytes)?;
+
+    Ok(())
+}
+
+fn run_batch(args: &Args, config: &Config) -> Result<(), Box<dyn Error>> {
+    let classes = read_input::<Vec<ClassInput>>(&args.input)?;
+
+    if classes.is_empty() {
+        return Err("no classes in the input".into());
+    }
+
+    let report = solver::batch::solve_batch(&classes, config)?;
+    for class in report.classes.iter() {
+        eprintln!(
+            "{}: score {}, seed {}, {} students moved",
+            class.name, class.score, class.seed, class.summary.moved
+        );
+    }
+
+    write_output(args, &serde_json::to_string_pretty(&report)?)?;
+
+    #[cfg(feature = "pdf")]
+    if let Some(path) = &args.pdf {
+        write_pdf(
+            path,
+            &report
+                .classes
+                .iter()
+                .map(|class| &class.layout)
+                .collect::<Vec<&Layout>>(),
+            config.objective,
+        )?;
+    }
+
+    Ok(())
+}
+
+fn run(args: &Args) -> Result<(), Box<dyn Error>> {
+    let constraints = match &args.constraints {
+        Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+        None => vec![],
+    };
+
+    let config = Config {
+        seed: args.seed,
+        constraints,
+        ..args.solver.config()
+    };
+
+    if args.batch {
+        return run_batch(args, &config);
+    }
+
+    let layout = read_input::<Layout>(&args.input)?;
+
+    if layout.iter().flatten().all(|seat| seat.is_none()) {
+        return Err("no students in the input layout".into());
+    }
+
+    let (new_layout, score) = solver::execute_with_config(&layout, &config)?;
+    eprintln!("score: {}", score);
+
+    write_output(args, &serde_json::to_string_pretty(&new_layout)?)?;
+
+    #[cfg(feature = "pdf")]
+    if let Some(path) = &args.pdf {
+        write_pdf(path, &[&new_layout], config.objective)?;
+    }
+
+    Ok(())
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    match run(&args) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use itertools::Itertools;
-use printpdf::{Line, Mm, PdfDocument, Point, Pt};
+use printpdf::{IndirectFontRef, Line, Mm, PdfDocument, PdfLayerReference, Point, Pt};
 
 const TTF_FILE: &[u8] = include_bytes!("../assets/fonts/ipaexg.ttf");
 
@@ -41,17 +41,41 @@ fn fit_font_size_to_rect(text: &str, max_font_size: Pt, width: Mm) -> Pt {
     Pt(right)
 }
 
+const PAGE_WIDTH: f64 = 297.0;
+const PAGE_HEIGHT: f64 = 210.0;
+
 pub fn gen(seats: Vec<Vec<String>>) -> Result<Vec<u8>, printpdf::Error> {
+    gen_pages(vec![seats])
+}
+
+/// One page per layout, e.g. for all homerooms of a grade.
+pub fn gen_pages(pages: Vec<Vec<Vec<String>>>) -> Result<Vec<u8>, printpdf::Error> {
+    let (doc, page1, layer1) =
+        PdfDocument::new("Seat Layout", Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "main");
+
+    let cursor = std::io::Cursor::new(TTF_FILE);
+    let font = doc.add_external_font(cursor).unwrap();
+
+    for (i, seats) in pages.iter().enumerate() {
+        let (page, layer) = if i == 0 {
+            (page1, layer1)
+        } else {
+            doc.add_page(Mm(PAGE_WIDTH), Mm(PAGE_HEIGHT), "main")
+        };
+        draw_seats(&doc.get_page(page).get_layer(layer), &font, seats);
+    }
+
+    doc.save_to_bytes()
+}
+
+fn draw_seats(current_layer: &PdfLayerReference, font: &IndirectFontRef, seats: &[Vec<String>]) {
     let (seat_width, seat_height) = (seats[0].len(), seats.len());
-    let (page_width, page_height) = (297.0, 210.0);
+    let (page_width, page_height) = (PAGE_WIDTH, PAGE_HEIGHT);
     let max_font_size = 17.0;
     let outline_margin_length = 15.0;
     let seat_margin_length = 5.0;
     let text_padding_length = 5.0;
 
-    let (doc, page1, layer1) =
-        PdfDocument::new("Seat Layout", Mm(page_width), Mm(page_height), "main");
-
     let (rect_width, rect_height) = (
         (page_width - outline_margin_length * 2.0 - seat_margin_length * (seat_width - 1) as f64)
             / seat_width as f64,
@@ -59,11 +83,6 @@ pub fn gen(seats: Vec<Vec<String>>) -> Result<Vec<u8>, printpdf::Error> {
             / seat_height as f64,
     );
 
-    let current_layer = doc.get_page(page1).get_layer(layer1);
-
-    let cursor = std::io::Cursor::new(TTF_FILE);
-    let font = doc.add_external_font(cursor).unwrap();
-
     for (j, i) in (0..seat_width).cartesian_product(0..seat_height) {
         let name = &seats[seat_height - i - 1][j];
 
@@ -125,11 +144,9 @@ pub fn gen(seats: Vec<Vec<String>>) -> Result<Vec<u8>, printpdf::Error> {
                 pt2mm(left_lower.y) + Mm(rect_height / 2.0) - pt2mm(Pt(17.0)) / 2.0,
             );
 
-            current_layer.use_text(name, font_size.0, text_x, text_y, &font);
+            current_layer.use_text(name, font_size.0, text_x, text_y, font);
         }
     }
-
-    doc.save_to_bytes()
 }
 
 #[cfg(test)]
@@ -163,4 +180,15 @@ mod tests {
         let bytes = gen(seats);
         assert!(bytes.is_ok());
     }
+
+    #[test]
+    fn gen_pages_works_with_no_error() {
+        let pages = vec![
+            vec![vec!["1".to_string(), "2".to_string()]],
+            vec![vec!["3".to_string()], vec!["".to_string()]],
+        ];
+
+        let bytes = gen_pages(pages);
+        assert!(bytes.is_ok());
+    }
 }
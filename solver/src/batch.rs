@@ -0,0 +1,215 @@
+use crate::{
+    diff::{diff, DiffSummary},
+    execute_with_config, input_seed,
+    structs::{Config, Constraint, Layout},
+};
+
+use std::{collections::HashSet, io::Error};
+
+/// One homeroom of a batch.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ClassInput {
+    pub name: String,
+    /// Current layout of the class.
+    pub layout: Layout,
+    /// Constraints of this class only, added to the shared ones.
+    #[serde(default)]
+    pub constraints: Vec<Constraint>,
+    /// Seed of the class, derived from the batch seed and the class when `None`.
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct ClassResult {
+    pub name: String,
+    /// Seed the class was solved with, which reproduces `layout` when given back.
+    pub seed: u64,
+    pub score: i64,
+    pub layout: Layout,
+    pub summary: DiffSummary,
+}
+
+/// Results of all classes of a batch, in the order of the input.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct BatchReport {
+    pub classes: Vec<ClassResult>,
+}
+
+/// Solves the classes in parallel. `config.constraints` are shared by all classes: each class
+/// gets those which only refer to its own students. Every student of a shared constraint must be
+/// in one of the classes.
+pub fn solve_batch(classes: &[ClassInput], config: &Config) -> Result<BatchReport, Error> {
+    let mut names = HashSet::new();
+    for class in classes.iter() {
+        if !names.insert(&class.name) {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Duplicated class name: {}", class.name),
+            ));
+        }
+    }
+
+    let ids = classes
+        .iter()
+        .flat_map(class_ids)
+        .collect::<HashSet<usize>>();
+    for constraint in config.constraints.iter() {
+        if let Some(id) = students(constraint)
+            .into_iter()
+            .find(|id| !ids.contains(id))
+        {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Student {} of a shared constraint is in no class", id),
+            ));
+        }
+    }
+
+    let results = std::thread::scope(|scope| {
+        let handles = classes
+            .iter()
+            .map(|class| scope.spawn(|| solve_class(class, config)))
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .map(|handle| {
+                handle
+                    .join()
+                    .unwrap_or_else(|_| Err(Error::other("Solver thread panicked")))
+            })
+            .collect::<Vec<Result<ClassResult, Error>>>()
+    });
+
+    let mut report = BatchReport::default();
+    for (class, result) in classes.iter().zip(results) {
+        report.classes.push(
+            result
+                .map_err(|err| Error::new(err.kind(), format!("Class {}: {}", class.name, err)))?,
+        );
+    }
+
+    Ok(report)
+}
+
+fn solve_class(class: &ClassInput, config: &Config) -> Result<ClassResult, Error> {
+    let seed = match (class.seed, config.seed) {
+        (Some(seed), _) => seed,
+        (None, Some(seed)) => input_seed(&[seed.to_string(), class.name.clone()]),
+        (None, None) => input_seed(&[class.name.clone(), serde_json::to_string(&class.layout)?]),
+    };
+
+    let ids = class_ids(class);
+    let constraints = config
+        .constraints
+        .iter()
+        .filter(|constraint| students(constraint).iter().all(|id| ids.contains(id)))
+        .chain(class.constraints.iter())
+        .cloned()
+        .collect();
+
+    let config = Config {
+        seed: Some(seed),
+        constraints,
+        ..config.clone()
+    };
+    let (layout, score) = execute_with_config(&class.layout, &config)?;
+    let summary = diff(&class.layout, &layout)?.summary;
+
+    Ok(ClassResult {
+        name: class.name.clone(),
+        seed,
+        score,
+        layout,
+        summary,
+    })
+}
+
+fn class_ids(class: &ClassInput) -> HashSet<usize> {
+    class
+        .layout
+        .iter()
+        .flatten()
+        .flatten()
+        .map(|student| student.id)
+        .collect()
+}
+
+fn students(constraint: &Constraint) -> Vec<usize> {
+    match *constraint {
+        Constraint::Pin { student, .. } | Constraint::Zone { student, .. } => vec![student],
+        Constraint::Separate { students } => students.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::scenario;
+
+    use super::*;
+
+    #[test]
+    fn batch_is_reproducible() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let classes = ["1-A", "1-B", "1-C"]
+            .iter()
+            .map(|name| ClassInput {
+                name: name.to_string(),
+                layout: scenario::generate(&Default::default(), &mut rng).unwrap(),
+                constraints: vec![],
+                seed: None,
+            })
+            .collect::<Vec<ClassInput>>();
+        let first_id =
+            |class: &ClassInput| class.layout.iter().flatten().flatten().next().unwrap().id;
+        let config = Config {
+            loop_cnt: 10000,
+            seed: Some(7),
+            // only applies to the classes which have both students
+            constraints: vec![Constraint::Separate {
+                students: [first_id(&classes[0]), first_id(&classes[1])],
+            }],
+            ..Default::default()
+        };
+
+        let report = solve_batch(&classes, &config).unwrap();
+        assert_eq!(
+            report
+                .classes
+                .iter()
+                .map(|class| class.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["1-A", "1-B", "1-C"]
+        );
+
+        // a class solved again with its reported seed gets the same layout
+        let class = ClassInput {
+            seed: Some(report.classes[1].seed),
+            ..classes[1].clone()
+        };
+        let again = solve_batch(
+            &[class],
+            &Config {
+                seed: None,
+                ..config.clone()
+            },
+        )
+        .unwrap();
+        assert_eq!(again.classes[0].layout, report.classes[1].layout);
+
+        let duplicated = vec![classes[0].clone(), classes[0].clone()];
+        assert!(solve_batch(&duplicated, &config).is_err());
+
+        let unknown = Config {
+            constraints: vec![Constraint::Separate {
+                students: [first_id(&classes[0]), 100000],
+            }],
+            ..config.clone()
+        };
+        assert!(solve_batch(&classes, &unknown).is_err());
+    }
+}
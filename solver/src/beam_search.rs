@@ -1,76 +1,221 @@
 use crate::{
-    eval_func::evaluate,
-    structs::{SeatAssignment, Student},
-    utils::swap_seats,
+    eval_func::{EvaluationContext, IncrementalEvaluator},
+    structs::{Config, PastLayouts, SeatAssignment, Student},
 };
 
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
 use std::{
-    collections::{BinaryHeap, VecDeque},
+    collections::HashSet,
     io::Error,
+    time::{Duration, Instant},
 };
 
+struct State<'a> {
+    evaluator: IncrementalEvaluator<'a>,
+    hash: u64,
+}
+
+/// Decides the student of one seat of `seats` after another, starting from `new` and keeping
+/// the `config.beam_width` best layouts. Layouts are scored incrementally and identical layouts
+/// reached by different swaps are kept only once.
 pub fn beam_search(
-    previous: &SeatAssignment,
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    seats: &[(usize, usize)],
     students: &[Student],
-    beam_width: usize,
+    config: &Config,
 ) -> Result<(SeatAssignment, i64), Error> {
-    let (depth, width, n) = (previous.len(), previous[0].len(), students.len());
+    if config.beam_width == 0 {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Beam width must be positive",
+        ));
+    }
 
-    let mut deq = VecDeque::new();
-    deq.push_back(previous.clone());
+    let context = EvaluationContext::new(previous, students, &config.weights)?;
+    let evaluator = IncrementalEvaluator::new(&context, new)?;
+    let (depth, width, n) = (new.len(), new[0].len(), students.len());
 
-    for x1 in 0..width {
-        for y1 in 0..depth {
-            if previous[y1][x1] == !0 {
-                continue;
-            }
+    let deadline = config
+        .time_limit_ms
+        .map(|ms| Instant::now() + Duration::from_millis(ms));
+
+    // zobrist hashing: the hash of a layout is the xor of the keys of its (seat, student) pairs,
+    // vacant seats have no key
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let keys = (0..depth * width)
+        .map(|_| (0..n).map(|_| rng.gen()).collect::<Vec<u64>>())
+        .collect::<Vec<Vec<u64>>>();
+    let key = |(x, y): (usize, usize), student_id: usize| {
+        if student_id == !0 {
+            0
+        } else {
+            keys[y * width + x][student_id]
+        }
+    };
+
+    let mut hash = 0;
+    for (y, row) in new.iter().enumerate() {
+        for (x, &student_id) in row.iter().enumerate() {
+            hash ^= key((x, y), student_id);
+        }
+    }
 
-            let mut heap = BinaryHeap::new();
-            loop {
-                if deq.is_empty() {
-                    break;
-                }
+    let mut beam = vec![State { evaluator, hash }];
+    let timed_out = || deadline.is_some_and(|deadline| Instant::now() >= deadline);
 
-                let current_layout = deq.pop_front().unwrap();
-                for x2 in 0..width {
-                    for y2 in 0..depth {
-                        if current_layout[y2][x2] == !0 {
-                            continue;
-                        }
+    'search: for &p1 in seats.iter() {
+        // (score, hash, index of the parent state, seat swapped with p1)
+        let mut candidates = vec![];
+        for (i, state) in beam.iter_mut().enumerate() {
+            // the beam so far is kept when the time is up in the middle of a step
+            if timed_out() {
+                break 'search;
+            }
 
-                        let mut new = current_layout.clone();
-                        swap_seats(&mut new, (x1, y1), (x2, y2));
+            for &p2 in seats.iter() {
+                let layout = state.evaluator.layout();
+                let (a, b) = (layout[p1.1][p1.0], layout[p2.1][p2.0]);
+                let hash = if p1 == p2 {
+                    state.hash
+                } else {
+                    state.hash ^ key(p1, a) ^ key(p2, b) ^ key(p1, b) ^ key(p2, a)
+                };
 
-                        let score = evaluate(previous, &new, students).unwrap();
+                state.evaluator.swap(p1, p2);
+                let score = state.evaluator.score()?;
+                state.evaluator.swap(p1, p2);
 
-                        heap.push((score, new));
-                    }
-                }
+                candidates.push((score, hash, i, p2));
             }
+        }
 
-            for _ in 0..beam_width {
-                if let Some((_, new)) = heap.pop() {
-                    deq.push_back(new);
-                }
+        candidates.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+
+        let mut seen = HashSet::new();
+        let mut next_beam = vec![];
+        for (_, hash, i, p2) in candidates {
+            if next_beam.len() == config.beam_width {
+                break;
+            }
+            if !seen.insert(hash) {
+                continue;
             }
+
+            let mut evaluator = beam[i].evaluator.clone();
+            evaluator.swap(p1, p2);
+            next_beam.push(State { evaluator, hash });
         }
+
+        beam = next_beam;
     }
 
-    let mut heap = BinaryHeap::new();
-    loop {
-        if deq.is_empty() {
-            break;
-        }
+    // the beam is sorted by score, so the first state is the best one
+    let best = beam.swap_remove(0).evaluator;
+    let score = best.score()?;
+
+    Ok((best.into_layout(), score))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::{
+        eval_func::evaluate,
+        moves::occupied_seats,
+        scenario::{self, ScenarioConfig},
+        utils::{compress_student_id, separate_input},
+    };
+
+    use super::*;
 
-        let layout = deq.pop_front().unwrap();
-        let score = evaluate(previous, &layout, students).unwrap();
+    fn generate(depth: usize, width: usize, vacancy_ratio: f64) -> (SeatAssignment, Vec<Student>) {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let config = ScenarioConfig {
+            depth,
+            width,
+            vacancy_ratio,
+            ..Default::default()
+        };
+        let layout = scenario::generate(&config, &mut rng).unwrap();
+        let (mut previous, mut students) = separate_input(&layout);
+        compress_student_id(&mut students, &mut previous).unwrap();
 
-        heap.push((score, layout));
+        (previous, students)
     }
 
-    if let Some((score, layout)) = heap.pop() {
-        return Ok((layout, score));
+    #[test]
+    fn beam_search_returns_permutation_with_its_score() {
+        let (previous, students) = generate(6, 6, 0.2);
+        let config = Config {
+            beam_width: 5,
+            ..Default::default()
+        };
+
+        let (new, score) = beam_search(
+            &previous,
+            &previous,
+            &occupied_seats(&previous),
+            &students,
+            &config,
+        )
+        .unwrap();
+
+        assert_eq!(occupied_seats(&new), occupied_seats(&previous));
+        let mut ids = new.iter().flatten().copied().collect::<Vec<usize>>();
+        ids.sort();
+        ids.retain(|&id| id != !0);
+        assert_eq!(ids, (0..students.len()).collect::<Vec<usize>>());
+
+        assert_eq!(
+            score,
+            evaluate(&previous, &new, &students, &config.weights).unwrap()
+        );
+        assert!(score >= evaluate(&previous, &previous, &students, &config.weights).unwrap());
+    }
+
+    #[test]
+    fn beam_search_respects_time_limit() {
+        let (previous, students) = generate(8, 8, 0.0);
+        let config = Config {
+            beam_width: 1000,
+            time_limit_ms: Some(100),
+            ..Default::default()
+        };
+
+        let start = Instant::now();
+        assert!(beam_search(
+            &previous,
+            &previous,
+            &occupied_seats(&previous),
+            &students,
+            &config,
+        )
+        .is_ok());
+        // the search stops within a step
+        assert!(start.elapsed() < Duration::from_millis(300));
     }
 
-    Err(Error::new(std::io::ErrorKind::Other, "Beam search failed."))
+    #[test]
+    fn zero_beam_width() {
+        let (previous, students) = generate(3, 3, 0.0);
+        let config = Config {
+            beam_width: 0,
+            ..Default::default()
+        };
+
+        assert!(beam_search(
+            &previous,
+            &previous,
+            &occupied_seats(&previous),
+            &students,
+            &config,
+        )
+        .is_err());
+    }
 }
@@ -0,0 +1,652 @@
+use crate::{
+    constraints::Rules,
+    eval_func::{blackboard_distance, evaluate, prev_adj_students},
+    moves::occupied_seats,
+    simulated_annealing,
+    structs::{
+        Config, Constraint, Gender, MoveStats, PastLayouts, SeatAssignment, Student, Weights,
+    },
+    utils::neighbourhood,
+};
+
+use rand_chacha::ChaCha20Rng;
+use std::{
+    io::Error,
+    time::{Duration, Instant},
+};
+
+/// Classes with more students are solved by simulated annealing alone.
+pub const MAX_STUDENTS: usize = 12;
+
+// covers truncating the individual scores, their mean and the balance scores to integers
+const SCORE_SLACK: f64 = 8.0;
+
+/// Finds an optimal layout by branch and bound, using the result of simulated annealing as the
+/// initial lower bound. The time limit only applies to the branch and bound; when it is
+/// exceeded, the best layout found so far is returned. Rooms with more seats than students are
+/// solved by simulated annealing alone.
+pub fn execute(
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    seats: &[(usize, usize)],
+    students: &[Student],
+    config: &Config,
+    rng: &mut ChaCha20Rng,
+) -> Result<(SeatAssignment, i64, MoveStats), Error> {
+    if students.len() > MAX_STUDENTS || seats.len() != students.len() {
+        return simulated_annealing::execute(previous, new, seats, students, config, rng);
+    }
+
+    let deadline = config
+        .time_limit_ms
+        .map(|ms| Instant::now() + Duration::from_millis(ms));
+
+    let (new, score, stats) = simulated_annealing::execute(
+        previous,
+        new,
+        seats,
+        students,
+        &Config {
+            time_limit_ms: None,
+            ..config.clone()
+        },
+        rng,
+    )?;
+    let (new, score) = branch_and_bound(
+        previous,
+        students,
+        &config.weights,
+        &config.constraints,
+        (new, score),
+        deadline,
+    )?;
+
+    Ok((new, score, stats))
+}
+
+/// Searches for a layout satisfying `constraints` and scoring higher than `incumbent`,
+/// returning the best layout found. The students take the occupied seats of `incumbent`.
+pub fn branch_and_bound(
+    previous: &dyn PastLayouts,
+    students: &[Student],
+    weights: &Weights,
+    constraints: &[Constraint],
+    incumbent: (SeatAssignment, i64),
+    deadline: Option<Instant>,
+) -> Result<(SeatAssignment, i64), Error> {
+    evaluate(previous, &incumbent.0, students, weights)?;
+    let (depth, width, n) = (incumbent.0.len(), incumbent.0[0].len(), students.len());
+
+    let mut adj_seat_cnt = vec![vec![0; width]; depth];
+    for &pos in occupied_seats(&incumbent.0).iter() {
+        for (x2, y2) in neighbourhood(pos, depth, width) {
+            adj_seat_cnt[y2][x2] += 1;
+        }
+    }
+
+    // fill the neighbourhood with the fewest empty seats first, so that the means of
+    // neighbourhoods are fixed early
+    let mut empty_seat_cnt = adj_seat_cnt.clone();
+    let mut seats = vec![];
+    let mut unfilled = occupied_seats(&incumbent.0);
+    while !unfilled.is_empty() {
+        let mut next = 0;
+        let mut min_cnt = i64::MAX;
+        for (l, &pos) in unfilled.iter().enumerate() {
+            for (x2, y2) in neighbourhood(pos, depth, width) {
+                if empty_seat_cnt[y2][x2] < min_cnt {
+                    min_cnt = empty_seat_cnt[y2][x2];
+                    next = l;
+                }
+            }
+        }
+
+        let pos = unfilled.remove(next);
+        for (x2, y2) in neighbourhood(pos, depth, width) {
+            empty_seat_cnt[y2][x2] -= 1;
+        }
+        seats.push(pos);
+    }
+
+    let seat_distances = seats
+        .iter()
+        .map(|&(x1, y1)| {
+            seats
+                .iter()
+                .map(|&(x2, y2)| (x1.abs_diff(x2) + y1.abs_diff(y2)) as f64)
+                .collect::<Vec<f64>>()
+        })
+        .collect::<Vec<Vec<f64>>>();
+    let seats_by_distance = (0..seats.len())
+        .map(|s| {
+            let mut others = (0..seats.len()).filter(|&t| t != s).collect::<Vec<usize>>();
+            others.sort_by(|&t1, &t2| seat_distances[s][t1].total_cmp(&seat_distances[s][t2]));
+            others
+        })
+        .collect();
+
+    let mut search = Search {
+        previous,
+        students,
+        weights,
+        prev_adj_students: prev_adj_students(previous, n),
+        rules: Rules::new(constraints, depth, width, n),
+        blackboard_distances: seats
+            .iter()
+            .map(|&pos| blackboard_distance(pos, width))
+            .collect(),
+        seat_distances,
+        seats_by_distance,
+        seats,
+        new: vec![vec![!0; width]; depth],
+        positions: vec![!0; n],
+        adj_sums: vec![vec![[0; 5]; width]; depth],
+        adj_seat_cnt,
+        best: incumbent,
+        deadline,
+        node_cnt: 0,
+        timed_out: false,
+    };
+    search.dfs(0)?;
+
+    Ok(search.best)
+}
+
+struct Search<'a> {
+    previous: &'a dyn PastLayouts,
+    students: &'a [Student],
+    weights: &'a Weights,
+    prev_adj_students: Vec<Vec<usize>>,
+    rules: Rules,
+    // occupied seats, filled in this order
+    seats: Vec<(usize, usize)>,
+    blackboard_distances: Vec<f64>,
+    seat_distances: Vec<Vec<f64>>,
+    // indices of the other seats, nearest first
+    seats_by_distance: Vec<Vec<usize>>,
+    // `!0` for seats which are not filled yet
+    new: SeatAssignment,
+    // index of the seat of each student, `!0` for unplaced students
+    positions: Vec<usize>,
+    // sums of academic, exercise and leadership abilities, male count and student count
+    // of the students placed within the 3x3 neighbourhood of each seat
+    adj_sums: Vec<Vec<[i64; 5]>>,
+    // number of occupied seats within the 3x3 neighbourhood of each seat
+    adj_seat_cnt: Vec<Vec<i64>>,
+    best: (SeatAssignment, i64),
+    deadline: Option<Instant>,
+    node_cnt: usize,
+    timed_out: bool,
+}
+
+fn values(student: &Student) -> [i64; 4] {
+    [
+        student.academic_ability as i64,
+        student.exercise_ability as i64,
+        student.leadership_ability as i64,
+        if student.gender == Gender::Male { 1 } else { 0 },
+    ]
+}
+
+/// Maximum total value of assigning each row to a distinct column of a square matrix
+/// (hungarian algorithm).
+fn max_assignment(values: &[Vec<f64>]) -> f64 {
+    let n = values.len();
+    let cost = |i: usize, j: usize| -values[i - 1][j - 1];
+
+    // potentials and matching are 1-indexed, column 0 is a sentinel
+    let (mut u, mut v) = (vec![0.0; n + 1], vec![0.0; n + 1]);
+    let (mut matched_row, mut way) = (vec![0; n + 1], vec![0; n + 1]);
+    for i in 1..=n {
+        matched_row[0] = i;
+        let mut j0 = 0;
+        let mut min_v = vec![f64::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+        loop {
+            used[j0] = true;
+            let i0 = matched_row[j0];
+            let (mut delta, mut j1) = (f64::INFINITY, 0);
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+                let reduced = cost(i0, j) - u[i0] - v[j];
+                if reduced < min_v[j] {
+                    min_v[j] = reduced;
+                    way[j] = j0;
+                }
+                if min_v[j] < delta {
+                    delta = min_v[j];
+                    j1 = j;
+                }
+            }
+            for j in 0..=n {
+                if used[j] {
+                    u[matched_row[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_v[j] -= delta;
+                }
+            }
+            j0 = j1;
+            if matched_row[j0] == 0 {
+                break;
+            }
+        }
+        while j0 != 0 {
+            let j1 = way[j0];
+            matched_row[j0] = matched_row[j1];
+            j0 = j1;
+        }
+    }
+
+    (1..=n).map(|j| values[matched_row[j] - 1][j - 1]).sum()
+}
+
+impl Search<'_> {
+    fn dfs(&mut self, filled: usize) -> Result<(), Error> {
+        self.node_cnt += 1;
+        if self.node_cnt.is_multiple_of(1024) && self.deadline.is_some_and(|d| Instant::now() >= d)
+        {
+            self.timed_out = true;
+        }
+        if self.timed_out {
+            return Ok(());
+        }
+
+        if filled == self.seats.len() {
+            let score = evaluate(self.previous, &self.new, self.students, self.weights)?;
+            if score > self.best.1 {
+                self.best = (self.new.clone(), score);
+            }
+            return Ok(());
+        }
+
+        if !self.can_improve(filled) {
+            return Ok(());
+        }
+
+        for i in 0..self.students.len() {
+            if self.positions[i] != !0 {
+                continue;
+            }
+
+            self.place(i, filled, 1);
+            if self.rules.satisfied_at(&self.new, self.seats[filled]) {
+                self.dfs(filled + 1)?;
+            }
+            self.place(i, filled, -1);
+        }
+
+        Ok(())
+    }
+
+    fn place(&mut self, i: usize, seat: usize, sign: i64) {
+        let (x, y) = self.seats[seat];
+        if sign > 0 {
+            self.new[y][x] = i;
+            self.positions[i] = seat;
+        } else {
+            self.new[y][x] = !0;
+            self.positions[i] = !0;
+        }
+
+        let values = values(&self.students[i]);
+        let (depth, width) = (self.new.len(), self.new[0].len());
+        for (x2, y2) in neighbourhood((x, y), depth, width) {
+            let sums = &mut self.adj_sums[y2][x2];
+            for (sum, value) in sums.iter_mut().zip(values) {
+                *sum += sign * value;
+            }
+            sums[4] += sign;
+        }
+    }
+
+    /// Upper bound of the individual score of student `i` sitting on `seat` when the seats from
+    /// `filled` on are still free.
+    fn seat_bound(&self, i: usize, seat: usize, filled: usize) -> f64 {
+        let weights = self.weights;
+        let prev_adj_students = &self.prev_adj_students[i];
+
+        let mut bound = 0.0;
+        if !prev_adj_students.is_empty() {
+            let (mut lo, mut hi, mut unplaced) = (0.0, 0.0, 0);
+            for &j in prev_adj_students.iter() {
+                if self.positions[j] == !0 {
+                    unplaced += 1;
+                } else {
+                    let d = self.seat_distances[seat][self.positions[j]];
+                    lo += d;
+                    hi += d;
+                }
+            }
+
+            // unplaced neighbours take distinct free seats, so their distances are at least the
+            // smallest and at most the largest distances to the free seats
+            let free = self.seats_by_distance[seat]
+                .iter()
+                .copied()
+                .filter(|&t| t >= filled);
+            for t in free.clone().take(unplaced) {
+                lo += self.seat_distances[seat][t];
+            }
+            for t in free.rev().take(unplaced) {
+                hi += self.seat_distances[seat][t];
+            }
+
+            let cnt = prev_adj_students.len() as f64;
+            bound = f64::max(
+                lo / cnt * weights.prev_adj_distance,
+                hi / cnt * weights.prev_adj_distance,
+            );
+        }
+        if self.students[i].needs_assistance {
+            bound -= self.blackboard_distances[seat] * weights.blackboard_distance;
+        }
+
+        bound
+    }
+
+    /// Whether a layout completing the seats filled so far may score higher than the best one.
+    fn can_improve(&self, filled: usize) -> bool {
+        let n = self.students.len();
+        let target = (self.best.1 + 1) as f64 - SCORE_SLACK;
+        let balance = self.balance_bound();
+
+        let n_f64 = n as f64;
+        let mut individual_sum = 0.0;
+        let mut matching = vec![];
+        for i in 0..n {
+            if self.positions[i] == !0 {
+                matching.push(
+                    (filled..self.seats.len())
+                        .map(|seat| self.seat_bound(i, seat, filled))
+                        .collect::<Vec<f64>>(),
+                );
+            } else {
+                individual_sum += self.seat_bound(i, self.positions[i], filled);
+            }
+        }
+
+        // letting every unplaced student take its best seat is cheaper to check than matching
+        // the unplaced students to the free seats one to one (gilmore-lawler bound)
+        let best_seats = matching
+            .iter()
+            .map(|row| row.iter().copied().fold(f64::NEG_INFINITY, f64::max))
+            .sum::<f64>();
+        if balance + (individual_sum + best_seats) / n_f64 < target {
+            return false;
+        }
+
+        balance + (individual_sum + max_assignment(&matching)) / n_f64 >= target
+    }
+
+    /// Upper bound of the balance scores of any layout completing the seats filled so far.
+    fn balance_bound(&self) -> f64 {
+        let n = self.students.len();
+        let weights = self.weights;
+
+        // the sums of the unplaced students sitting within a neighbourhood lie between the sums
+        // of the smallest and the largest remaining values
+        let balance_weights = [
+            weights.academic,
+            weights.exercise,
+            weights.leadership,
+            weights.gender,
+        ];
+        let mut bound = 0.0;
+        for (k, weight) in balance_weights.into_iter().enumerate() {
+            if weight <= 0.0 {
+                continue;
+            }
+
+            let mut remaining = [0; MAX_STUDENTS];
+            let mut remaining_cnt = 0;
+            for i in 0..n {
+                if self.positions[i] == !0 {
+                    remaining[remaining_cnt] = values(&self.students[i])[k];
+                    remaining_cnt += 1;
+                }
+            }
+            remaining[..remaining_cnt].sort();
+
+            let mut smallest = [0; MAX_STUDENTS + 1];
+            let mut largest = [0; MAX_STUDENTS + 1];
+            for l in 0..remaining_cnt {
+                smallest[l + 1] = smallest[l] + remaining[l];
+                largest[l + 1] = largest[l] + remaining[remaining_cnt - 1 - l];
+            }
+
+            let (mut min_hi, mut max_lo) = (f64::INFINITY, 0.0);
+            for (sums, seat_cnts) in self.adj_sums.iter().zip(self.adj_seat_cnt.iter()) {
+                for (sum, &seat_cnt) in sums.iter().zip(seat_cnts.iter()) {
+                    if seat_cnt == 0 {
+                        continue;
+                    }
+
+                    let unplaced = (seat_cnt - sum[4]) as usize;
+                    let lo = (sum[k] + smallest[unplaced]) as f64 / seat_cnt as f64;
+                    let hi = (sum[k] + largest[unplaced]) as f64 / seat_cnt as f64;
+                    min_hi = f64::min(min_hi, hi);
+                    max_lo = f64::max(max_lo, lo);
+                }
+            }
+
+            let ratio = if max_lo > 0.0 {
+                f64::min(min_hi / max_lo, 1.0)
+            } else {
+                1.0
+            };
+            bound += weight * ratio;
+        }
+
+        bound
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+
+    use crate::{
+        scenario::{self, ScenarioConfig},
+        utils::{compress_student_id, separate_input},
+    };
+
+    use super::*;
+
+    fn generate(
+        depth: usize,
+        width: usize,
+        vacancy_ratio: f64,
+        rng: &mut ChaCha20Rng,
+    ) -> (SeatAssignment, Vec<Student>) {
+        let config = ScenarioConfig {
+            depth,
+            width,
+            vacancy_ratio,
+            ..Default::default()
+        };
+        let layout = scenario::generate(&config, rng).unwrap();
+        let (mut previous, mut students) = separate_input(&layout);
+        compress_student_id(&mut students, &mut previous).unwrap();
+
+        (previous, students)
+    }
+
+    fn brute_force(
+        previous: &SeatAssignment,
+        new: &mut SeatAssignment,
+        students: &[Student],
+        seats: &[(usize, usize)],
+        filled: usize,
+        used: &mut Vec<bool>,
+    ) -> i64 {
+        if filled == seats.len() {
+            return evaluate(previous, new, students, &Weights::default()).unwrap();
+        }
+
+        let (x, y) = seats[filled];
+        let mut best = i64::MIN;
+        for i in 0..students.len() {
+            if used[i] {
+                continue;
+            }
+            used[i] = true;
+            new[y][x] = i;
+            best = best.max(brute_force(
+                previous,
+                new,
+                students,
+                seats,
+                filled + 1,
+                used,
+            ));
+            used[i] = false;
+        }
+
+        best
+    }
+
+    fn trivial_incumbent(previous: &SeatAssignment, students: &[Student]) -> (SeatAssignment, i64) {
+        let score = evaluate(previous, previous, students, &Weights::default()).unwrap();
+        (previous.clone(), score)
+    }
+
+    #[test]
+    fn branch_and_bound_matches_brute_force() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+
+        for (depth, width, vacancy_ratio) in [(2, 3, 0.0), (3, 3, 0.2), (2, 4, 0.1), (1, 5, 0.0)] {
+            let (previous, students) = generate(depth, width, vacancy_ratio, &mut rng);
+
+            let seats = occupied_seats(&previous);
+            let optimum = brute_force(
+                &previous,
+                &mut previous.clone(),
+                &students,
+                &seats,
+                0,
+                &mut vec![false; students.len()],
+            );
+
+            let (new, score) = branch_and_bound(
+                &previous,
+                &students,
+                &Weights::default(),
+                &[],
+                trivial_incumbent(&previous, &students),
+                None,
+            )
+            .unwrap();
+            assert_eq!(score, optimum);
+            assert_eq!(
+                evaluate(&previous, &new, &students, &Weights::default()).unwrap(),
+                optimum
+            );
+        }
+    }
+
+    #[test]
+    fn annealing_finds_optimum_on_tiny_instances() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+
+        for (depth, width, vacancy_ratio) in [(2, 3, 0.0), (3, 3, 0.0), (2, 4, 0.0), (3, 4, 0.3)] {
+            let (previous, students) = generate(depth, width, vacancy_ratio, &mut rng);
+
+            let (_, optimum) = branch_and_bound(
+                &previous,
+                &students,
+                &Weights::default(),
+                &[],
+                trivial_incumbent(&previous, &students),
+                None,
+            )
+            .unwrap();
+
+            // single runs occasionally end in a local optimum close to the global one
+            let scores = (0..4)
+                .map(|seed| {
+                    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+                    simulated_annealing::execute(
+                        &previous,
+                        &previous,
+                        &occupied_seats(&previous),
+                        &students,
+                        &Config::default(),
+                        &mut rng,
+                    )
+                    .unwrap()
+                    .1
+                })
+                .collect::<Vec<i64>>();
+            assert!(scores.iter().all(|&score| score <= optimum));
+            assert!(scores.iter().all(|&score| score >= optimum - optimum / 50));
+            assert!(scores.contains(&optimum));
+        }
+    }
+
+    #[test]
+    fn exact_improves_on_annealing_within_time_limit() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let (previous, students) = generate(3, 4, 0.0, &mut rng);
+        let config = Config {
+            time_limit_ms: Some(500),
+            ..Default::default()
+        };
+
+        let (new, score, _) = execute(
+            &previous,
+            &previous,
+            &occupied_seats(&previous),
+            &students,
+            &config,
+            &mut ChaCha20Rng::seed_from_u64(1),
+        )
+        .unwrap();
+        let (_, annealing_score, _) = simulated_annealing::execute(
+            &previous,
+            &previous,
+            &occupied_seats(&previous),
+            &students,
+            &Config::default(),
+            &mut ChaCha20Rng::seed_from_u64(1),
+        )
+        .unwrap();
+
+        assert!(score >= annealing_score);
+        assert_eq!(
+            score,
+            evaluate(&previous, &new, &students, &Weights::default()).unwrap()
+        );
+    }
+
+    #[test]
+    fn large_classes_fall_back_to_annealing() {
+        let (previous, students) = generate(5, 6, 0.0, &mut ChaCha20Rng::seed_from_u64(123));
+        let config = Config {
+            loop_cnt: 10000,
+            ..Default::default()
+        };
+
+        let exact = execute(
+            &previous,
+            &previous,
+            &occupied_seats(&previous),
+            &students,
+            &config,
+            &mut ChaCha20Rng::seed_from_u64(1),
+        )
+        .unwrap();
+        let annealing = simulated_annealing::execute(
+            &previous,
+            &previous,
+            &occupied_seats(&previous),
+            &students,
+            &config,
+            &mut ChaCha20Rng::seed_from_u64(1),
+        )
+        .unwrap();
+        assert_eq!(exact, annealing);
+    }
+}
@@ -0,0 +1,1014 @@
+use crate::{
+    moves::occupied_seats,
+    structs::{Constraint, SeatAssignment, Spacing, Student},
+    utils::{check_input, compress_student_id, neighbourhood, separate_input},
+};
+
+use std::io::Error;
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Feasibility {
+    Feasible,
+    /// No layout satisfies these constraints together, while any smaller part of them can be
+    /// satisfied.
+    Infeasible {
+        conflicts: Vec<Constraint>,
+    },
+}
+
+/// Checks whether the students of `current_layout` can be seated on its occupied seats so that
+/// all `constraints` hold. Students are given by their ids in `current_layout`.
+pub fn check_feasibility(
+    current_layout: &[Vec<Option<Student>>],
+    constraints: &[Constraint],
+) -> Result<Feasibility, Error> {
+    check_input(current_layout)?;
+
+    let (mut previous, mut students) = separate_input(current_layout);
+    let student_ids = students.iter().map(|s| s.id).collect::<Vec<usize>>();
+    compress_student_id(&mut students, &mut previous)?;
+
+    Ok(feasibility(
+        &previous,
+        &occupied_seats(&previous),
+        constraints,
+        &student_ids,
+    ))
+}
+
+/// Whether the students of `layout`, given by their indices in the sorted `student_ids`, can
+/// move between `seats` so that all `constraints` hold.
+pub(crate) fn feasibility(
+    layout: &SeatAssignment,
+    seats: &[(usize, usize)],
+    constraints: &[Constraint],
+    student_ids: &[usize],
+) -> Feasibility {
+    let compressed = compress_constraints(constraints, student_ids);
+    match minimal_conflict(layout, seats, &compressed, Spacing::Any, student_ids.len()) {
+        Some(conflicts) => Feasibility::Infeasible {
+            conflicts: conflicts
+                .into_iter()
+                .map(|i| constraints[i].clone())
+                .collect(),
+        },
+        None => Feasibility::Feasible,
+    }
+}
+
+/// Maps the student ids of `constraints` to their indices in the sorted `student_ids`. Unknown
+/// students are mapped to `student_ids.len()`, which no layout can satisfy.
+pub(crate) fn compress_constraints(
+    constraints: &[Constraint],
+    student_ids: &[usize],
+) -> Vec<Constraint> {
+    let compress = |id: usize| student_ids.binary_search(&id).unwrap_or(student_ids.len());
+
+    constraints
+        .iter()
+        .map(|constraint| match *constraint {
+            Constraint::Pin { student, x, y } => Constraint::Pin {
+                student: compress(student),
+                x,
+                y,
+            },
+            Constraint::Separate { students } => Constraint::Separate {
+                students: [compress(students[0]), compress(students[1])],
+            },
+            Constraint::Zone {
+                student,
+                rows,
+                columns,
+            } => Constraint::Zone {
+                student: compress(student),
+                rows,
+                columns,
+            },
+        })
+        .collect()
+}
+
+pub(crate) fn conflict_error(conflicts: &[Constraint]) -> Error {
+    Error::new(
+        std::io::ErrorKind::InvalidInput,
+        format!(
+            "Constraints cannot be satisfied together: {}",
+            conflicts
+                .iter()
+                .map(|c| c.to_string())
+                .collect::<Vec<String>>()
+                .join("; ")
+        ),
+    )
+}
+
+/// Constraints of a class with compressed student ids, for checking layouts quickly.
+#[derive(Clone, Debug)]
+pub(crate) struct Rules {
+    // seats each student may sit on, `None` when unrestricted
+    allowed: Vec<Option<Vec<Vec<bool>>>>,
+    separated: Vec<Vec<usize>>,
+    spacing: Spacing,
+    // some constraint refers to a student who is not in the class
+    unknown_student: bool,
+}
+
+impl Rules {
+    pub fn new(constraints: &[Constraint], depth: usize, width: usize, n: usize) -> Self {
+        let mut rules = Rules {
+            allowed: vec![None; n],
+            separated: vec![vec![]; n],
+            spacing: Spacing::Any,
+            unknown_student: false,
+        };
+
+        for constraint in constraints.iter() {
+            match *constraint {
+                Constraint::Pin { student, x, y } => {
+                    rules.restrict(student, depth, width, |x2, y2| (x2, y2) == (x, y));
+                }
+                Constraint::Separate { students: [a, b] } => {
+                    if a >= n || b >= n {
+                        rules.unknown_student = true;
+                    } else if a != b {
+                        rules.separated[a].push(b);
+                        rules.separated[b].push(a);
+                    }
+                }
+                Constraint::Zone {
+                    student,
+                    rows,
+                    columns,
+                } => {
+                    rules.restrict(student, depth, width, |x, y| {
+                        (rows[0]..=rows[1]).contains(&y) && (columns[0]..=columns[1]).contains(&x)
+                    });
+                }
+            }
+        }
+
+        rules
+    }
+
+    /// Also keeps the seats around each student vacant as `spacing` requires.
+    pub fn with_spacing(self, spacing: Spacing) -> Self {
+        Rules { spacing, ..self }
+    }
+
+    fn restrict<F: Fn(usize, usize) -> bool>(
+        &mut self,
+        student: usize,
+        depth: usize,
+        width: usize,
+        allows: F,
+    ) {
+        if student >= self.allowed.len() {
+            self.unknown_student = true;
+            return;
+        }
+
+        let allowed = self.allowed[student].get_or_insert_with(|| vec![vec![true; width]; depth]);
+        for (y, row) in allowed.iter_mut().enumerate() {
+            for (x, allowed) in row.iter_mut().enumerate() {
+                *allowed &= allows(x, y);
+            }
+        }
+    }
+
+    /// Whether the student can only sit on a single seat.
+    pub fn is_pinned(&self, student: usize) -> bool {
+        self.allowed[student]
+            .as_ref()
+            .is_some_and(|allowed| allowed.iter().flatten().filter(|&&a| a).count() == 1)
+    }
+
+    pub fn allows(&self, student: usize, (x, y): (usize, usize)) -> bool {
+        self.allowed[student]
+            .as_ref()
+            .is_none_or(|allowed| allowed[y][x])
+    }
+
+    /// Whether the student at `pos` sits on an allowed seat, apart from the students separated
+    /// from them and with the seats around them vacant as the spacing requires. Vacant seats are
+    /// always fine.
+    pub fn satisfied_at(&self, layout: &SeatAssignment, pos: (usize, usize)) -> bool {
+        let student = layout[pos.1][pos.0];
+        if student == !0 {
+            return true;
+        }
+        if !self.allows(student, pos) {
+            return false;
+        }
+
+        let separated = &self.separated[student];
+        if separated.is_empty() && self.spacing == Spacing::Any {
+            return true;
+        }
+        let (depth, width) = (layout.len(), layout[0].len());
+        for (x, y) in neighbourhood(pos, depth, width) {
+            if (x, y) == pos || layout[y][x] == !0 {
+                continue;
+            }
+            if separated.contains(&layout[y][x]) || !self.spacing.allows(pos, (x, y)) {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Seats the students of `new` on `seats` so that `constraints` hold and `spacing` is kept,
+/// leaving the unconstrained students on their seats where possible. Errors with a minimal set
+/// of conflicting constraints if that is impossible.
+pub(crate) fn feasible_layout(
+    new: &SeatAssignment,
+    seats: &[(usize, usize)],
+    constraints: &[Constraint],
+    spacing: Spacing,
+    n: usize,
+) -> Result<SeatAssignment, Error> {
+    let (depth, width) = (new.len(), new[0].len());
+    let rules = Rules::new(constraints, depth, width, n).with_spacing(spacing);
+    if let Some(layout) = find_layout(new, seats, &rules, n) {
+        return Ok(layout);
+    }
+
+    let unconstrained = Rules::new(&[], depth, width, n).with_spacing(spacing);
+    if find_layout(new, seats, &unconstrained, n).is_none() {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "Cannot keep the seats between {} students vacant with {} seats",
+                n,
+                seats.len()
+            ),
+        ));
+    }
+
+    let conflicts = minimal_conflict(new, seats, constraints, spacing, n).unwrap_or_default();
+    Err(conflict_error(
+        &conflicts
+            .into_iter()
+            .map(|i| constraints[i].clone())
+            .collect::<Vec<Constraint>>(),
+    ))
+}
+
+/// Indices of a minimal set of constraints which cannot be satisfied together, `None` if all
+/// of them can.
+fn minimal_conflict(
+    layout: &SeatAssignment,
+    seats: &[(usize, usize)],
+    constraints: &[Constraint],
+    spacing: Spacing,
+    n: usize,
+) -> Option<Vec<usize>> {
+    let (depth, width) = (layout.len(), layout[0].len());
+    let infeasible = |indices: &[usize]| {
+        let subset = indices
+            .iter()
+            .map(|&i| constraints[i].clone())
+            .collect::<Vec<Constraint>>();
+        let rules = Rules::new(&subset, depth, width, n).with_spacing(spacing);
+        find_layout(layout, seats, &rules, n).is_none()
+    };
+
+    let mut conflicts = (0..constraints.len()).collect::<Vec<usize>>();
+    if !infeasible(&conflicts) {
+        return None;
+    }
+
+    // drop every constraint which is not needed for the conflict
+    for i in 0..constraints.len() {
+        let rest = conflicts
+            .iter()
+            .copied()
+            .filter(|&j| j != i)
+            .collect::<Vec<usize>>();
+        if infeasible(&rest) {
+            conflicts = rest;
+        }
+    }
+
+    Some(conflicts)
+}
+
+fn find_layout(
+    layout: &SeatAssignment,
+    seats: &[(usize, usize)],
+    rules: &Rules,
+    n: usize,
+) -> Option<SeatAssignment> {
+    if rules.unknown_student {
+        return None;
+    }
+
+    let constrained = (0..n)
+        .filter(|&i| rules.allowed[i].is_some() || !rules.separated[i].is_empty())
+        .collect::<Vec<usize>>();
+    let domains = constrained
+        .iter()
+        .map(|&i| {
+            (0..seats.len())
+                .filter(|&s| rules.allows(i, seats[s]))
+                .collect::<Vec<usize>>()
+        })
+        .collect::<Vec<Vec<usize>>>();
+
+    // every constrained student needs an allowed seat of their own
+    if !has_matching(&domains, seats.len()) {
+        return None;
+    }
+
+    let mut base = layout.clone();
+    let mut others = vec![];
+    for &(x, y) in seats.iter() {
+        if base[y][x] != !0 && constrained.binary_search(&base[y][x]).is_err() {
+            others.push(((x, y), base[y][x]));
+        }
+        base[y][x] = !0;
+    }
+
+    let mut search = LayoutSearch {
+        rules,
+        seats,
+        constrained: &constrained,
+        domains: &domains,
+        seat_of: vec![!0; constrained.len()],
+        taken: vec![false; seats.len()],
+        base: &base,
+        others: &others,
+        spaced: vec![],
+    };
+    if !search.assign(0) {
+        return None;
+    }
+
+    let mut layout = base.clone();
+    for (c, &s) in search.seat_of.iter().enumerate() {
+        let (x, y) = seats[s];
+        layout[y][x] = constrained[c];
+    }
+    // seats left for the other students
+    let free = if rules.spacing == Spacing::Any {
+        seats
+            .iter()
+            .copied()
+            .filter(|&(x, y)| layout[y][x] == !0)
+            .collect()
+    } else {
+        search.spaced
+    };
+
+    // keep the other students on their seats where possible and seat the rest on the first
+    // free seats
+    let mut displaced = vec![];
+    for &(pos, student) in others.iter() {
+        if free.contains(&pos) && layout[pos.1][pos.0] == !0 {
+            layout[pos.1][pos.0] = student;
+        } else {
+            displaced.push(student);
+        }
+    }
+    for &(x, y) in free.iter() {
+        if displaced.is_empty() {
+            break;
+        }
+        if layout[y][x] == !0 {
+            layout[y][x] = displaced.pop()?;
+        }
+    }
+
+    displaced.is_empty().then_some(layout)
+}
+
+/// Rows with more spaced sets of seats than this are only searched over a few greedy sets.
+const MAX_ROW_MASKS: usize = 256;
+
+/// `cnt` seats among the vacant `seats` of `layout` such that no two of them and none of them
+/// and a student of `layout` are closer than `spacing` allows, keeping as many seats of `others`
+/// as possible. The spacing only relates seats of neighbouring rows, so the search goes row by
+/// row over the sets of seats taken in each row, see `row_masks`. `None` if no such seats are
+/// found or the layout has more than 64 columns.
+fn spaced_seats(
+    layout: &SeatAssignment,
+    seats: &[(usize, usize)],
+    others: &[((usize, usize), usize)],
+    cnt: usize,
+    spacing: Spacing,
+) -> Option<Vec<(usize, usize)>> {
+    let (depth, width) = (layout.len(), layout[0].len());
+    if width > 64 {
+        return None;
+    }
+
+    let mut usable = vec![0u64; depth];
+    for &(x, y) in seats.iter() {
+        let spaced = neighbourhood((x, y), depth, width)
+            .all(|(x2, y2)| layout[y2][x2] == !0 || spacing.allows((x, y), (x2, y2)));
+        if layout[y][x] == !0 && spaced {
+            usable[y] |= 1 << x;
+        }
+    }
+    let mut kept = vec![0u64; depth];
+    for &((x, y), _) in others.iter() {
+        kept[y] |= 1 << x;
+    }
+
+    // more seats always beat keeping more students on their seats
+    let seat_value = depth * width + 1;
+    // (seats taken in the row, value up to the row, index of the seats taken in the row before)
+    let mut rows: Vec<Vec<(u64, usize, usize)>> = vec![];
+    for y in 0..depth {
+        let row = row_masks(usable[y], kept[y], width, spacing)
+            .into_iter()
+            .filter_map(|mask| {
+                let value = mask.count_ones() as usize * seat_value
+                    + (mask & kept[y]).count_ones() as usize;
+                if y == 0 {
+                    return Some((mask, value, !0));
+                }
+                rows[y - 1]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, &(upper, _, _))| !rows_conflict(spacing, upper, mask))
+                    .map(|(i, &(_, upper_value, _))| (upper_value + value, i))
+                    .max_by_key(|&(value, i)| (value, std::cmp::Reverse(i)))
+                    .map(|(value, i)| (mask, value, i))
+            })
+            .collect::<Vec<(u64, usize, usize)>>();
+        rows.push(row);
+    }
+
+    let mut chosen = vec![];
+    // the first of the best sets, which leaves the rear seats vacant
+    let mut i = (0..rows.last()?.len())
+        .rev()
+        .max_by_key(|&i| rows[depth - 1][i].1)?;
+    for y in (0..depth).rev() {
+        let (mask, _, upper) = rows[y][i];
+        chosen.extend((0..width).filter(|&x| mask >> x & 1 == 1).map(|x| (x, y)));
+        i = upper;
+    }
+    if chosen.len() < cnt {
+        return None;
+    }
+
+    // any part of the chosen seats is spaced as well
+    chosen.sort_by_key(|&(x, y)| (kept[y] >> x & 1 == 0, y, x));
+    chosen.truncate(cnt);
+
+    Some(chosen)
+}
+
+/// Sets of the `usable` seats of a row, as bits, in which no two students sit closer than
+/// `spacing` allows. Wide rows have too many of them, so they only get the empty set and the
+/// sets filled greedily from every other seat or from the `kept` seats.
+fn row_masks(usable: u64, kept: u64, width: usize, spacing: Spacing) -> Vec<u64> {
+    let side_by_side = !spacing.allows((0, 0), (1, 0));
+
+    if spaced_set_cnt(usable, width, side_by_side) > MAX_ROW_MASKS {
+        let fill = |first: u64| {
+            let mut mask = 0u64;
+            for x in (0..width).filter(|&x| first >> x & 1 == 1).chain(0..width) {
+                let bit = 1u64 << x;
+                let beside = bit << 1 | bit >> 1;
+                if usable & bit != 0 && !(side_by_side && mask & beside != 0) {
+                    mask |= bit;
+                }
+            }
+            mask
+        };
+        let even = 0x5555_5555_5555_5555;
+
+        let mut masks = vec![0, fill(0), fill(even), fill(!even), fill(kept)];
+        masks.sort();
+        masks.dedup();
+        return masks;
+    }
+
+    let mut masks = vec![0];
+    for x in (0..width).filter(|&x| usable >> x & 1 == 1) {
+        let extended = masks
+            .iter()
+            .filter(|&&mask| !(side_by_side && x > 0 && mask >> (x - 1) & 1 == 1))
+            .map(|&mask| mask | 1 << x)
+            .collect::<Vec<u64>>();
+        masks.extend(extended);
+    }
+
+    masks
+}
+
+/// Number of sets `row_masks` lists for the `usable` seats, up to `usize::MAX`.
+fn spaced_set_cnt(usable: u64, width: usize, side_by_side: bool) -> usize {
+    // sets without and with the seat of the last column
+    let (mut without, mut with) = (1usize, 0usize);
+    for x in 0..width {
+        let taken = if usable >> x & 1 == 0 {
+            0
+        } else if side_by_side {
+            without
+        } else {
+            without.saturating_add(with)
+        };
+        without = without.saturating_add(with);
+        with = taken;
+    }
+
+    without.saturating_add(with)
+}
+
+/// Whether students on the seats `upper` of a row and `lower` of the row behind it sit closer
+/// than `spacing` allows.
+fn rows_conflict(spacing: Spacing, upper: u64, lower: u64) -> bool {
+    [(0, upper >> 1), (1, upper), (2, upper << 1)]
+        .into_iter()
+        .any(|(x, shifted)| !spacing.allows((1, 0), (x, 1)) && shifted & lower != 0)
+}
+
+/// Whether each row can be matched to a distinct column among the ones it lists (kuhn's
+/// algorithm).
+fn has_matching(domains: &[Vec<usize>], column_cnt: usize) -> bool {
+    fn augment(
+        row: usize,
+        domains: &[Vec<usize>],
+        matched_row: &mut [usize],
+        visited: &mut [bool],
+    ) -> bool {
+        for &column in domains[row].iter() {
+            if visited[column] {
+                continue;
+            }
+            visited[column] = true;
+            if matched_row[column] == !0
+                || augment(matched_row[column], domains, matched_row, visited)
+            {
+                matched_row[column] = row;
+                return true;
+            }
+        }
+        false
+    }
+
+    let mut matched_row = vec![!0; column_cnt];
+    (0..domains.len())
+        .all(|row| augment(row, domains, &mut matched_row, &mut vec![false; column_cnt]))
+}
+
+struct LayoutSearch<'a> {
+    rules: &'a Rules,
+    seats: &'a [(usize, usize)],
+    constrained: &'a [usize],
+    // seats allowed for each constrained student, as indices of `seats`
+    domains: &'a [Vec<usize>],
+    seat_of: Vec<usize>,
+    taken: Vec<bool>,
+    // layout without the students on `seats`
+    base: &'a SeatAssignment,
+    // unconstrained students on `seats` and their seats
+    others: &'a [((usize, usize), usize)],
+    // seats found for `others` when there is a spacing
+    spaced: Vec<(usize, usize)>,
+}
+
+impl LayoutSearch<'_> {
+    fn available(&self, c: usize, s: usize) -> bool {
+        if self.taken[s] {
+            return false;
+        }
+
+        let (x, y) = self.seats[s];
+        let spaced = self
+            .seat_of
+            .iter()
+            .all(|&s2| s2 == !0 || self.rules.spacing.allows(self.seats[s2], (x, y)));
+        spaced
+            && self.rules.separated[self.constrained[c]].iter().all(|&j| {
+                let Ok(d) = self.constrained.binary_search(&j) else {
+                    return true;
+                };
+                let s2 = self.seat_of[d];
+                s2 == !0 || {
+                    let (x2, y2) = self.seats[s2];
+                    x.abs_diff(x2) > 1 || y.abs_diff(y2) > 1
+                }
+            })
+    }
+
+    /// Seats the remaining constrained students, the one with the fewest available seats first.
+    fn assign(&mut self, assigned: usize) -> bool {
+        if assigned == self.constrained.len() {
+            return self.space_others();
+        }
+
+        let mut next = None;
+        let mut min_cnt = usize::MAX;
+        for c in 0..self.constrained.len() {
+            if self.seat_of[c] != !0 {
+                continue;
+            }
+            let cnt = self.domains[c]
+                .iter()
+                .filter(|&&s| self.available(c, s))
+                .count();
+            if cnt < min_cnt {
+                min_cnt = cnt;
+                next = Some(c);
+            }
+        }
+        let Some(c) = next else {
+            return self.space_others();
+        };
+
+        for &s in self.domains[c].iter() {
+            if !self.available(c, s) {
+                continue;
+            }
+
+            self.seat_of[c] = s;
+            self.taken[s] = true;
+            if self.assign(assigned + 1) {
+                return true;
+            }
+            self.seat_of[c] = !0;
+            self.taken[s] = false;
+        }
+
+        false
+    }
+
+    /// Finds spaced seats for the unconstrained students around the constrained ones.
+    fn space_others(&mut self) -> bool {
+        if self.rules.spacing == Spacing::Any {
+            return true;
+        }
+
+        let mut layout = self.base.clone();
+        for (c, &s) in self.seat_of.iter().enumerate() {
+            let (x, y) = self.seats[s];
+            layout[y][x] = self.constrained[c];
+        }
+        match spaced_seats(
+            &layout,
+            self.seats,
+            self.others,
+            self.others.len(),
+            self.rules.spacing,
+        ) {
+            Some(spaced) => {
+                self.spaced = spaced;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::{
+        scenario::{self, ScenarioConfig},
+        structs::{Algorithm, Config, Layout},
+    };
+
+    use super::*;
+
+    fn layout() -> Layout {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let config = ScenarioConfig {
+            depth: 4,
+            width: 5,
+            vacancy_ratio: 0.1,
+            first_id: 1,
+            max_id_gap: 2,
+            ..Default::default()
+        };
+        scenario::generate(&config, &mut rng).unwrap()
+    }
+
+    fn ids(layout: &Layout) -> Vec<usize> {
+        let mut ids = layout
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|s| s.id)
+            .collect::<Vec<usize>>();
+        ids.sort();
+        ids
+    }
+
+    fn position(layout: &Layout, id: usize) -> (usize, usize) {
+        for (y, row) in layout.iter().enumerate() {
+            for (x, seat) in row.iter().enumerate() {
+                if seat.as_ref().is_some_and(|s| s.id == id) {
+                    return (x, y);
+                }
+            }
+        }
+        panic!("student {} is not seated", id);
+    }
+
+    fn vacant_seat(layout: &Layout) -> (usize, usize) {
+        for (y, row) in layout.iter().enumerate() {
+            for (x, seat) in row.iter().enumerate() {
+                if seat.is_none() {
+                    return (x, y);
+                }
+            }
+        }
+        panic!("no vacant seat");
+    }
+
+    #[test]
+    fn feasible_constraints() {
+        let layout = layout();
+        let ids = ids(&layout);
+        let constraints = vec![
+            Constraint::Pin {
+                student: ids[0],
+                x: 0,
+                y: 0,
+            },
+            Constraint::Separate {
+                students: [ids[0], ids[1]],
+            },
+            Constraint::Zone {
+                student: ids[1],
+                rows: [0, 1],
+                columns: [0, 4],
+            },
+        ];
+
+        assert_eq!(
+            check_feasibility(&layout, &constraints).unwrap(),
+            Feasibility::Feasible
+        );
+        assert_eq!(
+            check_feasibility(&layout, &[]).unwrap(),
+            Feasibility::Feasible
+        );
+    }
+
+    #[test]
+    fn conflicting_pins() {
+        let layout = layout();
+        let ids = ids(&layout);
+        let pin = |student: usize, (x, y): (usize, usize)| Constraint::Pin { student, x, y };
+
+        let same_seat = vec![
+            pin(ids[0], (0, 0)),
+            pin(ids[2], (1, 1)),
+            pin(ids[1], (0, 0)),
+        ];
+        assert_eq!(
+            check_feasibility(&layout, &same_seat).unwrap(),
+            Feasibility::Infeasible {
+                conflicts: vec![pin(ids[0], (0, 0)), pin(ids[1], (0, 0))]
+            }
+        );
+
+        for seat in [vacant_seat(&layout), (5, 0)] {
+            let constraints = vec![pin(ids[0], (0, 0)), pin(ids[1], seat)];
+            assert_eq!(
+                check_feasibility(&layout, &constraints).unwrap(),
+                Feasibility::Infeasible {
+                    conflicts: vec![pin(ids[1], seat)]
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn crowded_zone() {
+        let layout = layout();
+        let ids = ids(&layout);
+        let zone = |student: usize| Constraint::Zone {
+            student,
+            rows: [0, 0],
+            columns: [0, 1],
+        };
+        let constraints = vec![
+            zone(ids[0]),
+            Constraint::Separate {
+                students: [ids[5], ids[6]],
+            },
+            zone(ids[1]),
+            zone(ids[2]),
+        ];
+
+        assert_eq!(
+            check_feasibility(&layout, &constraints).unwrap(),
+            Feasibility::Infeasible {
+                conflicts: vec![zone(ids[0]), zone(ids[1]), zone(ids[2])]
+            }
+        );
+    }
+
+    #[test]
+    fn separation_of_neighbours() {
+        let layout = layout();
+        let ids = ids(&layout);
+        let constraints = vec![
+            Constraint::Pin {
+                student: ids[0],
+                x: 2,
+                y: 2,
+            },
+            Constraint::Zone {
+                student: ids[1],
+                rows: [1, 3],
+                columns: [1, 3],
+            },
+            Constraint::Separate {
+                students: [ids[0], ids[1]],
+            },
+        ];
+
+        assert_eq!(
+            check_feasibility(&layout, &constraints).unwrap(),
+            Feasibility::Infeasible {
+                conflicts: constraints.clone()
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_student() {
+        let layout = layout();
+        let ids = ids(&layout);
+        let constraints = vec![
+            Constraint::Separate {
+                students: [ids[0], 1000],
+            },
+            Constraint::Pin {
+                student: ids[0],
+                x: 0,
+                y: 0,
+            },
+        ];
+
+        assert_eq!(
+            check_feasibility(&layout, &constraints).unwrap(),
+            Feasibility::Infeasible {
+                conflicts: vec![constraints[0].clone()]
+            }
+        );
+    }
+
+    #[test]
+    fn spaced_layouts() {
+        let new = vec![vec![0, 1, 2, 3], vec![!0; 4]];
+        let seats = (0..2)
+            .flat_map(|y| (0..4).map(move |x| (x, y)))
+            .collect::<Vec<(usize, usize)>>();
+
+        let spaced = feasible_layout(&new, &seats, &[], Spacing::Horizontal, 4).unwrap();
+        assert_eq!(spaced, vec![vec![0, !0, 2, !0], vec![3, !0, 1, !0]]);
+        let rules = Rules::new(&[], 2, 4, 4).with_spacing(Spacing::Horizontal);
+        assert!(seats.iter().all(|&pos| rules.satisfied_at(&spaced, pos)));
+        assert!(!rules.satisfied_at(&new, (0, 0)));
+
+        // a pinned student is kept apart from the others as well
+        let pinned = vec![Constraint::Pin {
+            student: 1,
+            x: 1,
+            y: 1,
+        }];
+        let spaced = feasible_layout(&new, &seats, &pinned, Spacing::Orthogonal, 4).unwrap();
+        assert_eq!(spaced, vec![vec![0, !0, 2, !0], vec![!0, 1, !0, 3]]);
+
+        // students bunched at the back of a row spread out over it
+        let row = (0..5).map(|x| (x, 0)).collect::<Vec<(usize, usize)>>();
+        let back_loaded = vec![vec![0, !0, !0, 1, 2]];
+        assert_eq!(
+            feasible_layout(&back_loaded, &row, &[], Spacing::Horizontal, 3).unwrap(),
+            vec![vec![0, !0, 1, !0, 2]]
+        );
+        let contiguous = vec![vec![!0, 0, 1, 2, !0]];
+        assert_eq!(
+            feasible_layout(&contiguous, &row, &[], Spacing::Horizontal, 3).unwrap(),
+            vec![vec![2, !0, 1, !0, 0]]
+        );
+
+        // only two students fit when the seats around each of them stay vacant
+        assert!(feasible_layout(&new, &seats, &[], Spacing::Surrounding, 4).is_err());
+        let two = vec![vec![0, 1, !0, !0], vec![!0; 4]];
+        assert_eq!(
+            feasible_layout(&two, &seats, &[], Spacing::Surrounding, 2).unwrap(),
+            vec![vec![0, !0, 1, !0], vec![!0; 4]]
+        );
+    }
+
+    #[test]
+    fn spaced_layouts_of_a_wide_room() {
+        let (depth, width) = (5, 30);
+        let seats = (0..depth)
+            .flat_map(|y| (0..width).map(move |x| (x, y)))
+            .collect::<Vec<(usize, usize)>>();
+
+        for (spacing, n) in [
+            (Spacing::Horizontal, 75),
+            (Spacing::Orthogonal, 75),
+            (Spacing::Surrounding, 45),
+        ] {
+            let mut new = vec![vec![!0; width]; depth];
+            for i in 0..n {
+                new[i / width][i % width] = i;
+            }
+
+            let spaced = feasible_layout(&new, &seats, &[], spacing, n).unwrap();
+            let rules = Rules::new(&[], depth, width, n).with_spacing(spacing);
+            assert!(seats.iter().all(|&pos| rules.satisfied_at(&spaced, pos)));
+            assert_eq!(spaced.iter().flatten().filter(|&&i| i != !0).count(), n);
+
+            let mut crowded = new.clone();
+            crowded[depth - 1][width - 1] = n;
+            assert!(feasible_layout(&crowded, &seats, &[], spacing, n + 1).is_err());
+        }
+    }
+
+    #[test]
+    fn solver_keeps_constraints() {
+        let layout = layout();
+        let ids = ids(&layout);
+        let constraints = vec![
+            Constraint::Pin {
+                student: ids[0],
+                x: 4,
+                y: 3,
+            },
+            Constraint::Separate {
+                students: [ids[1], ids[2]],
+            },
+            Constraint::Separate {
+                students: [ids[1], ids[3]],
+            },
+            Constraint::Zone {
+                student: ids[1],
+                rows: [0, 0],
+                columns: [0, 4],
+            },
+            Constraint::Zone {
+                student: ids[4],
+                rows: [0, 1],
+                columns: [0, 1],
+            },
+        ];
+        assert_eq!(
+            check_feasibility(&layout, &constraints).unwrap(),
+            Feasibility::Feasible
+        );
+
+        for algorithm in [Algorithm::SimulatedAnnealing, Algorithm::BranchAndBound] {
+            let config = Config {
+                algorithm,
+                loop_cnt: 20000,
+                constraints: constraints.clone(),
+                ..Default::default()
+            };
+            let (new, _) = crate::execute_with_config(&layout, &config).unwrap();
+
+            assert_eq!(position(&new, ids[0]), (4, 3));
+            assert_eq!(position(&new, ids[1]).1, 0);
+            let (x, y) = position(&new, ids[4]);
+            assert!(x <= 1 && y <= 1);
+            for other in [ids[2], ids[3]] {
+                let (x1, y1) = position(&new, ids[1]);
+                let (x2, y2) = position(&new, other);
+                assert!(x1.abs_diff(x2) > 1 || y1.abs_diff(y2) > 1);
+            }
+        }
+    }
+
+    #[test]
+    fn solver_rejects_conflicts() {
+        let layout = layout();
+        let ids = ids(&layout);
+        let constraints = vec![
+            Constraint::Pin {
+                student: ids[0],
+                x: 0,
+                y: 0,
+            },
+            Constraint::Pin {
+                student: ids[1],
+                x: 0,
+                y: 0,
+            },
+        ];
+
+        for algorithm in [Algorithm::SimulatedAnnealing, Algorithm::BeamSearch] {
+            let config = Config {
+                algorithm,
+                constraints: constraints.clone(),
+                ..Default::default()
+            };
+            assert!(crate::execute_with_config(&layout, &config).is_err());
+        }
+    }
+}
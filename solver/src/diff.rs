@@ -0,0 +1,218 @@
+use crate::{eval_func::DIR, export::seats, structs::Student, utils::check_input};
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Error,
+};
+
+/// Seats of one student in two layouts, as (row, column) counted from 1 like on the PDF. A seat
+/// is empty if the student is missing from that layout, and so is the Manhattan distance between
+/// the seats.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct StudentMove {
+    pub id: usize,
+    pub name: String,
+    pub from: Option<(usize, usize)>,
+    pub to: Option<(usize, usize)>,
+    pub distance: Option<usize>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct DiffSummary {
+    pub moved: usize,
+    pub stayed: usize,
+    pub joined: usize,
+    pub left: usize,
+    /// Over the students of both layouts, 0 if there are none.
+    pub mean_distance: f64,
+    pub max_distance: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LayoutDiff {
+    pub moves: Vec<StudentMove>,
+    /// Ids of the students of both layouts who have the same neighbours, including diagonal
+    /// ones, as before.
+    pub same_neighbours: Vec<usize>,
+    pub summary: DiffSummary,
+}
+
+/// Moves of the students from `previous` to `new`, sorted by id.
+pub fn diff(
+    previous: &[Vec<Option<Student>>],
+    new: &[Vec<Option<Student>>],
+) -> Result<LayoutDiff, Error> {
+    let moves = moves(previous, new)?;
+
+    let (previous_neighbours, new_neighbours) = (neighbours(previous), neighbours(new));
+    let same_neighbours = moves
+        .iter()
+        .filter(|m| m.distance.is_some())
+        .filter(|m| previous_neighbours.get(&m.id) == new_neighbours.get(&m.id))
+        .map(|m| m.id)
+        .collect();
+
+    let distances = moves
+        .iter()
+        .filter_map(|m| m.distance)
+        .collect::<Vec<usize>>();
+    let summary = DiffSummary {
+        moved: distances.iter().filter(|&&d| d > 0).count(),
+        stayed: distances.iter().filter(|&&d| d == 0).count(),
+        joined: moves.iter().filter(|m| m.from.is_none()).count(),
+        left: moves.iter().filter(|m| m.to.is_none()).count(),
+        mean_distance: if distances.is_empty() {
+            0.0
+        } else {
+            distances.iter().sum::<usize>() as f64 / distances.len() as f64
+        },
+        max_distance: distances.iter().copied().max().unwrap_or(0),
+    };
+
+    Ok(LayoutDiff {
+        moves,
+        same_neighbours,
+        summary,
+    })
+}
+
+fn neighbours(layout: &[Vec<Option<Student>>]) -> BTreeMap<usize, BTreeSet<usize>> {
+    let mut neighbours = BTreeMap::new();
+    for (y, row) in layout.iter().enumerate() {
+        for (x, student) in row.iter().enumerate() {
+            let Some(student) = student else {
+                continue;
+            };
+
+            let ids = DIR
+                .iter()
+                .filter_map(|d| {
+                    let (x2, y2) = (x as i32 + d[0], y as i32 + d[1]);
+                    if x2 < 0 || y2 < 0 {
+                        return None;
+                    }
+                    layout
+                        .get(y2 as usize)
+                        .and_then(|row| row.get(x2 as usize))
+                        .and_then(|seat| seat.as_ref())
+                        .map(|neighbour| neighbour.id)
+                })
+                .collect();
+            neighbours.insert(student.id, ids);
+        }
+    }
+
+    neighbours
+}
+
+/// One move per student of either layout, sorted by id.
+pub fn moves(
+    previous: &[Vec<Option<Student>>],
+    new: &[Vec<Option<Student>>],
+) -> Result<Vec<StudentMove>, Error> {
+    check_input(previous)?;
+    check_input(new)?;
+
+    let mut moves = BTreeMap::new();
+    for (row, column, student) in seats(previous) {
+        moves.insert(
+            student.id,
+            StudentMove {
+                id: student.id,
+                name: student.name.clone(),
+                from: Some((row, column)),
+                to: None,
+                distance: None,
+            },
+        );
+    }
+    for (row, column, student) in seats(new) {
+        moves
+            .entry(student.id)
+            .or_insert_with(|| StudentMove {
+                id: student.id,
+                name: student.name.clone(),
+                from: None,
+                to: None,
+                distance: None,
+            })
+            .to = Some((row, column));
+    }
+
+    let mut moves = moves.into_values().collect::<Vec<StudentMove>>();
+    for m in moves.iter_mut() {
+        if let (Some(from), Some(to)) = (m.from, m.to) {
+            m.distance = Some(from.0.abs_diff(to.0) + from.1.abs_diff(to.1));
+        }
+    }
+
+    Ok(moves)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::Gender;
+
+    use super::*;
+
+    fn student(id: usize) -> Option<Student> {
+        Some(Student {
+            id,
+            name: id.to_string(),
+            academic_ability: 3,
+            exercise_ability: 3,
+            leadership_ability: 3,
+            needs_assistance: false,
+            gender: Gender::Male,
+        })
+    }
+
+    #[test]
+    fn moves_of_staying_joining_and_leaving_students() {
+        let previous = vec![vec![student(1), student(2)], vec![student(4), None]];
+        let new = vec![vec![student(1), student(3)], vec![None, student(2)]];
+
+        assert_eq!(
+            moves(&previous, &new)
+                .unwrap()
+                .into_iter()
+                .map(|m| (m.id, m.from, m.to, m.distance))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, Some((1, 1)), Some((1, 1)), Some(0)),
+                (2, Some((1, 2)), Some((2, 2)), Some(1)),
+                (3, None, Some((1, 2)), None),
+                (4, Some((2, 1)), None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn neighbours_and_summary() {
+        // 1 and 2 swap places, 3 stays and 4 moves next to 1
+        let previous = vec![
+            vec![student(1), student(2), None],
+            vec![None, student(3), None],
+            vec![None, None, student(4)],
+        ];
+        let new = vec![
+            vec![student(2), student(1), student(4)],
+            vec![None, student(3), None],
+            vec![None, None, None],
+        ];
+
+        let diff = diff(&previous, &new).unwrap();
+        assert_eq!(diff.same_neighbours, vec![2, 3]);
+        assert_eq!(
+            diff.summary,
+            DiffSummary {
+                moved: 3,
+                stayed: 1,
+                joined: 0,
+                left: 0,
+                mean_distance: 1.0,
+                max_distance: 2,
+            }
+        );
+    }
+}
@@ -1,15 +1,15 @@
-use std::io::Error;
+use std::{collections::BTreeMap, io::Error};
 
-use crate::structs::{Gender, SeatAssignment, Student};
+use crate::{
+    rotation::{zone_counts, zone_repeats},
+    structs::{
+        Gender, Objective, PastLayouts, ScoreComponents, SeatAssignment, Student, TestVersions,
+        Weights,
+    },
+    utils::{layout_size, neighbourhood, swap_seats},
+};
 
-const PREV_ADJ_DISTANCE_WEIGHT: f64 = 1000.0;
-const BLACKBOARD_DISTANCE_WEIGHT: f64 = 1000.0;
-const ACADEMIC_WEIGHT: f64 = 1000.0;
-const EXERCISE_WEIGHT: f64 = 1000.0;
-const LEADERSHIP_WEIGHT: f64 = 1000.0;
-const GENDER_WEIGHT: f64 = 1000.0;
-
-const DIR: [[i32; 2]; 8] = [
+pub(crate) const DIR: [[i32; 2]; 8] = [
     [0, 1],
     [1, 0],
     [0, -1],
@@ -21,240 +21,814 @@ const DIR: [[i32; 2]; 8] = [
 ];
 
 pub fn evaluate(
-    previous: &SeatAssignment,
+    previous: &dyn PastLayouts,
     new: &SeatAssignment,
     students: &[Student],
+    weights: &Weights,
 ) -> Result<i64, Error> {
-    let (depth, width, n) = (previous.len(), previous[0].len(), students.len());
-
-    if let Ok(individual_scores) = individual(previous, new, students) {
-        let mut score = (individual_scores.iter().sum::<i64>() as f64 / n as f64) as i64;
-
-        let (
-            mut adj_academic_means,
-            mut adj_exercise_means,
-            mut adj_leadership_means,
-            mut adj_male_rate,
-            mut adj_cnt,
-        ) = (
-            vec![vec![0.0; width]; depth],
-            vec![vec![0.0; width]; depth],
-            vec![vec![0.0; width]; depth],
-            vec![vec![0.0; width]; depth],
-            vec![vec![0; width]; depth],
-        );
+    let individual_scores = individual(previous, new, students, weights)?;
+    let n = students.len();
 
-        for x in 0..width {
-            for y in 0..depth {
-                let student_id = new[y][x];
-                if student_id == !0 {
-                    continue;
-                }
+    let score = (individual_scores.iter().sum::<i64>() as f64 / n as f64) as i64;
+
+    Ok(add_balance_scores(
+        score,
+        balance_ranges(new, students)?,
+        weights,
+    ))
+}
 
-                let imos_pos_scaler = [
-                    (x as i64 - 1, y as i64 - 1, 1),
-                    (x as i64 + 2, y as i64 - 1, -1),
-                    (x as i64 - 1, y as i64 + 2, -1),
-                    (x as i64 + 2, y as i64 + 2, 1),
-                ];
+/// Terms of the score `evaluate` gives to `new`.
+pub(crate) fn score_components(
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    students: &[Student],
+    weights: &Weights,
+) -> Result<ScoreComponents, Error> {
+    let individual_scores = individual(previous, new, students, weights)?;
+    let n = students.len();
+    let ranges = balance_ranges(new, students)?;
 
-                for &(x2, y2, scaler) in imos_pos_scaler.iter() {
-                    let (i, j) = (y2.max(0) as usize, x2.max(0) as usize);
+    Ok(ScoreComponents {
+        individual: (individual_scores.iter().sum::<i64>() as f64 / n as f64) as i64,
+        academic: balance_term(ranges[0], weights.academic),
+        exercise: balance_term(ranges[1], weights.exercise),
+        leadership: balance_term(ranges[2], weights.leadership),
+        gender: balance_term(ranges[3], weights.gender),
+    })
+}
 
-                    if i >= depth || j >= width {
-                        continue;
-                    }
+/// Balance terms of the score of `new`, which `evaluate` adds to the mean individual score.
+pub(crate) fn balance_score(
+    new: &SeatAssignment,
+    students: &[Student],
+    weights: &Weights,
+) -> Result<i64, Error> {
+    Ok(add_balance_scores(
+        0,
+        balance_ranges(new, students)?,
+        weights,
+    ))
+}
+
+/// (min, max) of the neighbourhood means of academic, exercise and leadership abilities and of
+/// the male rate over the seats of `new`.
+fn balance_ranges(new: &SeatAssignment, students: &[Student]) -> Result<[(f64, f64); 4], Error> {
+    let (depth, width) = (new.len(), new[0].len());
+
+    let (
+        mut adj_academic_means,
+        mut adj_exercise_means,
+        mut adj_leadership_means,
+        mut adj_male_rate,
+        mut adj_cnt,
+    ) = (
+        vec![vec![0.0; width]; depth],
+        vec![vec![0.0; width]; depth],
+        vec![vec![0.0; width]; depth],
+        vec![vec![0.0; width]; depth],
+        vec![vec![0; width]; depth],
+    );
+
+    for (y, row) in new.iter().enumerate() {
+        for (x, &student_id) in row.iter().enumerate() {
+            if student_id == !0 {
+                continue;
+            }
+
+            let imos_pos_scaler = [
+                (x as i64 - 1, y as i64 - 1, 1),
+                (x as i64 + 2, y as i64 - 1, -1),
+                (x as i64 - 1, y as i64 + 2, -1),
+                (x as i64 + 2, y as i64 + 2, 1),
+            ];
+
+            for &(x2, y2, scaler) in imos_pos_scaler.iter() {
+                let (i, j) = (y2.max(0) as usize, x2.max(0) as usize);
 
-                    adj_academic_means[i][j] +=
-                        (scaler * students[student_id].academic_ability as i64) as f64;
-                    adj_exercise_means[i][j] +=
-                        (scaler * students[student_id].exercise_ability as i64) as f64;
-                    adj_leadership_means[i][j] +=
-                        (scaler * students[student_id].leadership_ability as i64) as f64;
-                    adj_male_rate[i][j] += (scaler
-                        * if students[student_id].gender == Gender::Male {
-                            1
-                        } else {
-                            0
-                        }) as f64;
-                    adj_cnt[i][j] += scaler;
+                if i >= depth || j >= width {
+                    continue;
                 }
+
+                adj_academic_means[i][j] +=
+                    (scaler * students[student_id].academic_ability as i64) as f64;
+                adj_exercise_means[i][j] +=
+                    (scaler * students[student_id].exercise_ability as i64) as f64;
+                adj_leadership_means[i][j] +=
+                    (scaler * students[student_id].leadership_ability as i64) as f64;
+                adj_male_rate[i][j] += (scaler
+                    * if students[student_id].gender == Gender::Male {
+                        1
+                    } else {
+                        0
+                    }) as f64;
+                adj_cnt[i][j] += scaler;
             }
         }
+    }
 
-        for y in 0..depth {
-            for x in 1..width {
-                adj_academic_means[y][x] += adj_academic_means[y][x - 1];
-                adj_exercise_means[y][x] += adj_exercise_means[y][x - 1];
-                adj_leadership_means[y][x] += adj_leadership_means[y][x - 1];
-                adj_male_rate[y][x] += adj_male_rate[y][x - 1];
-                adj_cnt[y][x] += adj_cnt[y][x - 1];
-            }
+    for y in 0..depth {
+        for x in 1..width {
+            adj_academic_means[y][x] += adj_academic_means[y][x - 1];
+            adj_exercise_means[y][x] += adj_exercise_means[y][x - 1];
+            adj_leadership_means[y][x] += adj_leadership_means[y][x - 1];
+            adj_male_rate[y][x] += adj_male_rate[y][x - 1];
+            adj_cnt[y][x] += adj_cnt[y][x - 1];
         }
+    }
 
-        for x in 0..width {
-            for y in 1..depth {
-                adj_academic_means[y][x] += adj_academic_means[y - 1][x];
-                adj_exercise_means[y][x] += adj_exercise_means[y - 1][x];
-                adj_leadership_means[y][x] += adj_leadership_means[y - 1][x];
-                adj_male_rate[y][x] += adj_male_rate[y - 1][x];
-                adj_cnt[y][x] += adj_cnt[y - 1][x];
-            }
+    for x in 0..width {
+        for y in 1..depth {
+            adj_academic_means[y][x] += adj_academic_means[y - 1][x];
+            adj_exercise_means[y][x] += adj_exercise_means[y - 1][x];
+            adj_leadership_means[y][x] += adj_leadership_means[y - 1][x];
+            adj_male_rate[y][x] += adj_male_rate[y - 1][x];
+            adj_cnt[y][x] += adj_cnt[y - 1][x];
         }
+    }
 
-        for x in 0..width {
-            for y in 0..depth {
-                adj_academic_means[y][x] /= adj_cnt[y][x] as f64;
-                adj_exercise_means[y][x] /= adj_cnt[y][x] as f64;
-                adj_leadership_means[y][x] /= adj_cnt[y][x] as f64;
-                adj_male_rate[y][x] /= adj_cnt[y][x] as f64;
-            }
+    for x in 0..width {
+        for y in 0..depth {
+            adj_academic_means[y][x] /= adj_cnt[y][x] as f64;
+            adj_exercise_means[y][x] /= adj_cnt[y][x] as f64;
+            adj_leadership_means[y][x] /= adj_cnt[y][x] as f64;
+            adj_male_rate[y][x] /= adj_cnt[y][x] as f64;
         }
+    }
 
-        let (academic_min, academic_max) = (
-            adj_academic_means
-                .iter()
-                .flatten()
-                .filter(|&&x| !x.is_nan())
-                .min_by(|x, y| x.partial_cmp(y).unwrap())
-                .unwrap(),
-            adj_academic_means
-                .iter()
-                .flatten()
-                .filter(|&&x| !x.is_nan())
-                .max_by(|x, y| x.partial_cmp(y).unwrap())
-                .unwrap(),
-        );
-        let (exercise_min, exercise_max) = (
-            adj_exercise_means
-                .iter()
-                .flatten()
-                .filter(|&&x| !x.is_nan())
-                .min_by(|x, y| x.partial_cmp(y).unwrap())
-                .unwrap(),
-            adj_exercise_means
-                .iter()
-                .flatten()
-                .filter(|&&x| !x.is_nan())
-                .max_by(|x, y| x.partial_cmp(y).unwrap())
-                .unwrap(),
-        );
-        let (leadership_min, leadership_max) = (
-            adj_leadership_means
-                .iter()
-                .flatten()
-                .filter(|&&x| !x.is_nan())
-                .min_by(|x, y| x.partial_cmp(y).unwrap())
-                .unwrap(),
-            adj_leadership_means
-                .iter()
-                .flatten()
-                .filter(|&&x| !x.is_nan())
-                .max_by(|x, y| x.partial_cmp(y).unwrap())
-                .unwrap(),
-        );
+    Ok([
+        min_max(&adj_academic_means)?,
+        min_max(&adj_exercise_means)?,
+        min_max(&adj_leadership_means)?,
+        min_max(&adj_male_rate)?,
+    ])
+}
 
-        let (male_rate_min, male_rate_max) = (
-            adj_male_rate
-                .iter()
-                .flatten()
-                .filter(|&&x| !x.is_nan())
-                .min_by(|x, y| x.partial_cmp(y).unwrap())
-                .unwrap(),
-            adj_male_rate
-                .iter()
-                .flatten()
-                .filter(|&&x| !x.is_nan())
-                .max_by(|x, y| x.partial_cmp(y).unwrap())
-                .unwrap(),
-        );
+/// Adds the balance terms given the (min, max) of the neighbourhood means of academic,
+/// exercise and leadership abilities and of the male rate.
+fn add_balance_scores(mut score: i64, ranges: [(f64, f64); 4], weights: &Weights) -> i64 {
+    let balance_weights = [
+        weights.academic,
+        weights.exercise,
+        weights.leadership,
+        weights.gender,
+    ];
+    for (range, weight) in ranges.into_iter().zip(balance_weights) {
+        score = score.saturating_add(balance_term(range, weight));
+    }
 
-        score += (ACADEMIC_WEIGHT * (academic_min / academic_max)) as i64;
-        score += (EXERCISE_WEIGHT * (exercise_min / exercise_max)) as i64;
-        score += (LEADERSHIP_WEIGHT * (leadership_min / leadership_max)) as i64;
-        score += (GENDER_WEIGHT * (male_rate_min / male_rate_max)) as i64;
+    score
+}
 
-        return Ok(score);
-    }
+fn balance_term((min, max): (f64, f64), weight: f64) -> i64 {
+    (weight * (min / max)) as i64
+}
 
-    Err(Error::new(
-        std::io::ErrorKind::Other,
-        "Something went wrong",
-    ))
+fn min_max(values: &[Vec<f64>]) -> Result<(f64, f64), Error> {
+    values
+        .iter()
+        .flatten()
+        .filter(|x| !x.is_nan())
+        .fold(None, |acc, &x| match acc {
+            None => Some((x, x)),
+            Some((min, max)) => Some((f64::min(min, x), f64::max(max, x))),
+        })
+        .ok_or_else(|| Error::new(std::io::ErrorKind::InvalidInput, "No students are seated"))
 }
 
+/// Individual scores of the students. The layouts may have different sizes; students missing
+/// from `previous` have no previous neighbours.
 pub fn individual(
-    previous: &SeatAssignment,
+    previous: &dyn PastLayouts,
     new: &SeatAssignment,
     students: &[Student],
+    weights: &Weights,
 ) -> Result<Vec<i64>, Error> {
-    let (depth, width, n) = (previous.len(), previous[0].len(), students.len());
+    let (depth, width) = layout_size(new)?;
+    let n = students.len();
+
+    let positions = checked_positions(previous, new, n)?;
 
     // distance between prev_adj_students and student
-    let mut before_after_positions = vec![((!0, !0), (!0, !0)); n];
+    let prev_adj_students = prev_adj_students(previous, n);
+    let zone_counts = zone_counts(previous, n);
+    let mut individual_scores = vec![0; n];
+    for i in 0..n {
+        let (x1, y1) = positions[i];
+        let mut sum = 0.0;
+        for &j in prev_adj_students[i].iter() {
+            let (x2, y2) = positions[j];
+            sum += ((x1 as i32 - x2 as i32).abs() + (y1 as i32 - y2 as i32).abs()) as f64;
+        }
+        individual_scores[i] = individual_score(
+            &students[i],
+            (x1, y1),
+            width,
+            sum / prev_adj_students[i].len() as f64,
+            zone_repeats(&zone_counts[i], (x1, y1), depth, width),
+            weights,
+        );
+    }
+
+    Ok(individual_scores)
+}
+
+/// Score of `new` for an exam: the mean distance of each student to the closest student they
+/// sat next to in `previous`, times `weights.prev_adj_distance`, minus `weights.same_version`
+/// for each pair of students side by side or one behind the other who write the same version
+/// of the test. Students without previous neighbours only count for the versions.
+pub fn evaluate_exam(
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    students: &[Student],
+    weights: &Weights,
+    versions: TestVersions,
+) -> Result<i64, Error> {
+    let (depth, width) = layout_size(new)?;
+    let n = students.len();
+    let positions = checked_positions(previous, new, n)?;
+
+    let prev_adj_students = prev_adj_students(previous, n);
+    let closest = (0..n)
+        .filter_map(|i| {
+            let (x1, y1) = positions[i];
+            prev_adj_students[i]
+                .iter()
+                .map(|&j| {
+                    let (x2, y2) = positions[j];
+                    x1.abs_diff(x2) + y1.abs_diff(y2)
+                })
+                .min()
+        })
+        .collect::<Vec<usize>>();
+    let mut score = if closest.is_empty() {
+        0
+    } else {
+        (closest.iter().sum::<usize>() as f64 / closest.len() as f64 * weights.prev_adj_distance)
+            as i64
+    };
+
+    let mut same_version = 0;
     for y in 0..depth {
         for x in 0..width {
-            let before_student_id = previous[y][x];
-            let after_student_id = new[y][x];
+            let Some(version) = test_version(versions, (x, y), new[y][x]) else {
+                continue;
+            };
+            for (x2, y2) in [(x + 1, y), (x, y + 1)] {
+                if x2 < width
+                    && y2 < depth
+                    && test_version(versions, (x2, y2), new[y2][x2]) == Some(version)
+                {
+                    same_version += 1;
+                }
+            }
+        }
+    }
+    score -= (same_version as f64 * weights.same_version) as i64;
+
+    Ok(score)
+}
+
+/// Score of `new` under `objective`.
+pub fn objective_score(
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    students: &[Student],
+    weights: &Weights,
+    objective: Objective,
+) -> Result<i64, Error> {
+    match objective {
+        Objective::Regular => evaluate(previous, new, students, weights),
+        Objective::Exam { versions } => evaluate_exam(previous, new, students, weights, versions),
+    }
+}
+
+/// Version of the test, 0 for A and 1 for B, written by `student` at `pos`. Students are
+/// counted in the order of their ids. `None` for vacant seats and when there is a single version.
+pub(crate) fn test_version(
+    versions: TestVersions,
+    (x, y): (usize, usize),
+    student: usize,
+) -> Option<usize> {
+    if student == !0 {
+        return None;
+    }
 
-            if (before_student_id == !0 && after_student_id != !0)
-                || (before_student_id != !0 && after_student_id == !0)
-            {
+    match versions {
+        TestVersions::Single => None,
+        TestVersions::Checkerboard => Some((x + y) % 2),
+        TestVersions::AttendanceOrder => Some(student % 2),
+    }
+}
+
+/// Seat of each student in `new`, checking that the layouts are valid and that every student
+/// is seated exactly once in `new`.
+fn checked_positions(
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    n: usize,
+) -> Result<Vec<(usize, usize)>, Error> {
+    check_previous(previous, n)?;
+    new_positions(new, n)
+}
+
+fn check_previous(previous: &dyn PastLayouts, n: usize) -> Result<(), Error> {
+    for layout in previous.layouts() {
+        layout_size(layout)?;
+        seat_positions(layout, n, "previous")?;
+    }
+
+    Ok(())
+}
+
+/// Seat of each student in `new`, where every student must be seated.
+fn new_positions(new: &SeatAssignment, n: usize) -> Result<Vec<(usize, usize)>, Error> {
+    seat_positions(new, n, "new")?
+        .into_iter()
+        .enumerate()
+        .map(|(i, pos)| {
+            pos.ok_or_else(|| {
+                Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Student {} is not seated exactly once", i),
+                )
+            })
+        })
+        .collect()
+}
+
+/// Seat of each student in `layout`, `None` for students who are not seated.
+fn seat_positions(
+    layout: &SeatAssignment,
+    n: usize,
+    name: &str,
+) -> Result<Vec<Option<(usize, usize)>>, Error> {
+    let mut positions = vec![None; n];
+    for (y, row) in layout.iter().enumerate() {
+        for (x, &student_id) in row.iter().enumerate() {
+            if student_id == !0 {
+                continue;
+            }
+
+            if student_id >= n {
+                return Err(Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Student id out of range",
+                ));
+            }
+            if positions[student_id].is_some() {
                 return Err(Error::new(
                     std::io::ErrorKind::InvalidInput,
-                    "Invalid input",
+                    format!(
+                        "Student {} is seated more than once in the {} layout",
+                        student_id, name
+                    ),
                 ));
             }
+            positions[student_id] = Some((x, y));
+        }
+    }
 
-            if before_student_id == !0 {
-                continue;
+    Ok(positions)
+}
+
+fn individual_score(
+    student: &Student,
+    pos: (usize, usize),
+    width: usize,
+    prev_adj_distance_mean: f64,
+    zone_repeats: usize,
+    weights: &Weights,
+) -> i64 {
+    let mut score = (prev_adj_distance_mean * weights.prev_adj_distance) as i64;
+    score -= (zone_repeats as f64 * weights.zone_repeat) as i64;
+
+    if student.needs_assistance {
+        let distance_penalty =
+            (blackboard_distance(pos, width) * weights.blackboard_distance) as i64;
+        score -= distance_penalty;
+    }
+
+    score
+}
+
+/// Distance between the blackboard and the seat at `pos`.
+pub(crate) fn blackboard_distance(pos: (usize, usize), width: usize) -> f64 {
+    let (x_blackboard, y_blackboard) = (width as f64 / 2.0, -1.0);
+    let (x, y) = pos;
+
+    ((x as f64 - x_blackboard).powf(2.0) + (y as f64 - y_blackboard).powf(2.0)).sqrt()
+}
+
+/// Students sitting next to each student in the previous layouts, once per layout.
+pub(crate) fn prev_adj_students(previous: &dyn PastLayouts, n: usize) -> Vec<Vec<usize>> {
+    let mut prev_adj_students = vec![vec![]; n];
+    for layout in previous.layouts() {
+        let (depth, width) = (layout.len(), layout[0].len());
+        for y in 0..depth {
+            for x in 0..width {
+                if layout[y][x] == !0 {
+                    continue;
+                }
+                for d in DIR {
+                    let (x2, y2) = (x as i32 + d[0], y as i32 + d[1]);
+                    if x2 < 0 || x2 >= width as i32 || y2 < 0 || y2 >= depth as i32 {
+                        continue;
+                    }
+                    let adj_student_id = layout[y2 as usize][x2 as usize];
+                    if adj_student_id != !0 {
+                        prev_adj_students[layout[y][x]].push(adj_student_id);
+                    }
+                }
             }
+        }
+    }
+
+    prev_adj_students
+}
+
+/// Tables of the students and their past layouts, which do not change while students are
+/// swapped. One context is shared by all `IncrementalEvaluator`s of a search.
+#[derive(Clone, Debug)]
+pub struct EvaluationContext<'a> {
+    students: &'a [Student],
+    weights: Weights,
+    prev_adj_students: Vec<Vec<usize>>,
+    zone_counts: Vec<[usize; 4]>,
+}
+
+impl<'a> EvaluationContext<'a> {
+    pub fn new(
+        previous: &dyn PastLayouts,
+        students: &'a [Student],
+        weights: &Weights,
+    ) -> Result<Self, Error> {
+        let n = students.len();
+        check_previous(previous, n)?;
 
-            before_after_positions[before_student_id].0 = (x, y);
-            before_after_positions[after_student_id].1 = (x, y);
+        Ok(EvaluationContext {
+            students,
+            weights: *weights,
+            prev_adj_students: prev_adj_students(previous, n),
+            zone_counts: zone_counts(previous, n),
+        })
+    }
+}
+
+/// Keeps the score of a layout up to date while students are swapped, recomputing only the
+/// individual scores and neighbourhood sums around the swapped seats.
+#[derive(Clone, Debug)]
+pub struct IncrementalEvaluator<'a> {
+    context: &'a EvaluationContext<'a>,
+    new: SeatAssignment,
+    positions: Vec<(usize, usize)>,
+    individual_scores: Vec<i64>,
+    individual_score_sum: i64,
+    // sums of academic, exercise and leadership abilities, male count and student count
+    // within the 3x3 neighbourhood of each seat
+    adj_sums: Vec<Vec<[i64; 5]>>,
+    // how many seats have each mean of the first four sums, keyed by the bits of the mean, which
+    // are ordered like the non-negative means
+    means: [BTreeMap<u64, usize>; 4],
+}
+
+impl<'a> IncrementalEvaluator<'a> {
+    pub fn new(context: &'a EvaluationContext<'a>, new: &SeatAssignment) -> Result<Self, Error> {
+        let n = context.students.len();
+        let positions = new_positions(new, n)?;
+        let (depth, width) = (new.len(), new[0].len());
+
+        let mut evaluator = IncrementalEvaluator {
+            context,
+            new: new.clone(),
+            positions,
+            individual_scores: vec![0; n],
+            individual_score_sum: 0,
+            adj_sums: vec![vec![[0; 5]; width]; depth],
+            means: Default::default(),
+        };
+        for i in 0..n {
+            let score = evaluator.individual_score(i);
+            evaluator.individual_scores[i] = score;
+            evaluator.individual_score_sum += score;
+            evaluator.update_adj_sums(i, 1);
         }
+
+        Ok(evaluator)
     }
 
-    let mut prev_adj_distance_means = vec![0.0; n];
-    for i in 0..n {
+    pub fn layout(&self) -> &SeatAssignment {
+        &self.new
+    }
+
+    pub fn into_layout(self) -> SeatAssignment {
+        self.new
+    }
+
+    /// Same values as `individual` for the current layout.
+    pub fn individual_scores(&self) -> &[i64] {
+        &self.individual_scores
+    }
+
+    /// Same as `rotate` of `moves`.
+    pub fn rotate(&mut self, cycle: &[(usize, usize)]) {
+        for i in 1..cycle.len() {
+            self.swap(cycle[0], cycle[i]);
+        }
+    }
+
+    /// Same value as `evaluate` for the current layout.
+    pub fn score(&self) -> Result<i64, Error> {
+        let n = self.context.students.len();
+        let score = (self.individual_score_sum as f64 / n as f64) as i64;
+
+        let mut ranges = [(0.0, 0.0); 4];
+        for (range, means) in ranges.iter_mut().zip(self.means.iter()) {
+            let (Some((&min, _)), Some((&max, _))) =
+                (means.first_key_value(), means.last_key_value())
+            else {
+                return Err(Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "No students are seated",
+                ));
+            };
+            *range = (f64::from_bits(min), f64::from_bits(max));
+        }
+
+        Ok(add_balance_scores(score, ranges, &self.context.weights))
+    }
+
+    /// Swaps the students at `pos1` and `pos2`. Either seat may be vacant.
+    pub fn swap(&mut self, pos1: (usize, usize), pos2: (usize, usize)) {
+        let (a, b) = (self.new[pos1.1][pos1.0], self.new[pos2.1][pos2.0]);
+        if a == b {
+            return;
+        }
+        let moved = [a, b]
+            .into_iter()
+            .filter(|&i| i != !0)
+            .collect::<Vec<usize>>();
+
+        for &i in moved.iter() {
+            self.update_adj_sums(i, -1);
+        }
+        swap_seats(&mut self.new, pos1, pos2);
+        if a != !0 {
+            self.positions[a] = pos2;
+        }
+        if b != !0 {
+            self.positions[b] = pos1;
+        }
+        for &i in moved.iter() {
+            self.update_adj_sums(i, 1);
+        }
+
+        let mut affected = moved.clone();
+        for &i in moved.iter() {
+            affected.extend(self.context.prev_adj_students[i].iter());
+        }
+        affected.sort();
+        affected.dedup();
+
+        for i in affected {
+            let score = self.individual_score(i);
+            self.individual_score_sum += score - self.individual_scores[i];
+            self.individual_scores[i] = score;
+        }
+    }
+
+    fn individual_score(&self, i: usize) -> i64 {
+        let context = self.context;
+        let (x1, y1) = self.positions[i];
+
         let mut sum = 0.0;
-        let (x_prev, y_prev) = before_after_positions[i].0;
-        let mut prev_adj_cnt = 0;
-        for d in DIR {
-            let (x, y) = ((x_prev as i32 + d[0]), (y_prev as i32 + d[1]));
-            if x < 0 || x >= width as i32 || y < 0 || y >= depth as i32 {
-                continue;
+        for &j in context.prev_adj_students[i].iter() {
+            let (x2, y2) = self.positions[j];
+            sum += ((x1 as i32 - x2 as i32).abs() + (y1 as i32 - y2 as i32).abs()) as f64;
+        }
+        let prev_adj_distance_mean = sum / context.prev_adj_students[i].len() as f64;
+
+        let (depth, width) = (self.new.len(), self.new[0].len());
+        individual_score(
+            &context.students[i],
+            self.positions[i],
+            width,
+            prev_adj_distance_mean,
+            zone_repeats(&context.zone_counts[i], self.positions[i], depth, width),
+            &context.weights,
+        )
+    }
+
+    fn update_adj_sums(&mut self, i: usize, sign: i64) {
+        let student = &self.context.students[i];
+        let values = [
+            student.academic_ability as i64,
+            student.exercise_ability as i64,
+            student.leadership_ability as i64,
+            if student.gender == Gender::Male { 1 } else { 0 },
+            1,
+        ];
+
+        let (x, y) = self.positions[i];
+        let (depth, width) = (self.adj_sums.len(), self.adj_sums[0].len());
+        for (x2, y2) in neighbourhood((x, y), depth, width) {
+            self.count_means((x2, y2), -1);
+            for (sum, value) in self.adj_sums[y2][x2].iter_mut().zip(values) {
+                *sum = sum.wrapping_add(sign * value);
             }
-            let adj_student_id = previous[y as usize][x as usize];
-            if adj_student_id != !0 {
-                let (x1, y1) = before_after_positions[i].1;
-                let (x2, y2) = before_after_positions[adj_student_id].1;
+            self.count_means((x2, y2), 1);
+        }
+    }
+
+    /// Adds the means of the seat at `(x, y)` to `means`, or removes them with a negative `sign`.
+    fn count_means(&mut self, (x, y): (usize, usize), sign: i64) {
+        let sums = self.adj_sums[y][x];
+        if sums[4] == 0 {
+            return;
+        }
 
-                sum += ((x1 as i32 - x2 as i32).abs() + (y1 as i32 - y2 as i32).abs()) as f64;
-                prev_adj_cnt += 1;
+        for (means, &sum) in self.means.iter_mut().zip(sums.iter()) {
+            let key = (sum as f64 / sums[4] as f64).to_bits();
+            if sign > 0 {
+                *means.entry(key).or_insert(0) += 1;
+            } else if let Some(cnt) = means.get_mut(&key) {
+                *cnt -= 1;
+                if *cnt == 0 {
+                    means.remove(&key);
+                }
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{seq::index, Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::{
+        moves::occupied_seats,
+        scenario::{self, ScenarioConfig},
+        utils::{compress_student_id, separate_input},
+    };
+
+    use super::*;
 
-        prev_adj_distance_means[i] = sum / prev_adj_cnt as f64;
+    #[test]
+    fn newcomers_have_no_history() {
+        let students = (0..3)
+            .map(|i| Student {
+                id: i,
+                name: format!("Student {}", i),
+                academic_ability: 3,
+                exercise_ability: 3,
+                leadership_ability: 3,
+                needs_assistance: false,
+                gender: Gender::Male,
+            })
+            .collect::<Vec<Student>>();
+        // student 2 is new and the room has a second row
+        let previous = vec![vec![0, 1]];
+        let new = vec![vec![0, !0], vec![2, 1]];
+
+        let scores = individual(&previous, &new, &students, &Weights::default()).unwrap();
+        assert_eq!(scores, vec![2000, 2000, 0]);
+        assert!(evaluate(&previous, &new, &students, &Weights::default()).is_ok());
+
+        let missing = vec![vec![0, !0], vec![!0, 1]];
+        assert!(individual(&previous, &missing, &students, &Weights::default()).is_err());
+        let twice = vec![vec![0, 0], vec![2, 1]];
+        assert!(individual(&previous, &twice, &students, &Weights::default()).is_err());
     }
 
-    // distance between blackboard and student
-    let mut blackboard_distances = vec![0.0; n];
-    let (x_blackboard, y_blackboard) = (width as f64 / 2.0, -1.0);
-    for i in 0..n {
-        let (x, y) = before_after_positions[i].1;
-        blackboard_distances[i] =
-            ((x as f64 - x_blackboard).powf(2.0) + (y as f64 - y_blackboard).powf(2.0)).sqrt();
+    #[test]
+    fn neighbours_of_every_past_layout() {
+        let history = vec![
+            vec![vec![0, 1, 2]],
+            vec![vec![2, 0, 1]],
+            vec![vec![0, 1, 2]],
+        ];
+
+        let mut adjacent = prev_adj_students(&history, 3);
+        adjacent.iter_mut().for_each(|ids| ids.sort());
+        assert_eq!(
+            adjacent,
+            vec![vec![1, 1, 1, 2], vec![0, 0, 0, 2, 2], vec![0, 1, 1]]
+        );
     }
 
-    let mut individual_scores = vec![0; n];
-    for i in 0..n {
-        individual_scores[i] = (prev_adj_distance_means[i] * PREV_ADJ_DISTANCE_WEIGHT) as i64;
-        if students[i].needs_assistance {
-            let distance_penalty = (blackboard_distances[i] * BLACKBOARD_DISTANCE_WEIGHT) as i64;
-            individual_scores[i] -= distance_penalty;
-        }
+    #[test]
+    fn repeated_zones() {
+        let students = (0..2)
+            .map(|i| Student {
+                id: i,
+                name: format!("Student {}", i),
+                academic_ability: 3,
+                exercise_ability: 3,
+                leadership_ability: 3,
+                needs_assistance: false,
+                gender: Gender::Male,
+            })
+            .collect::<Vec<Student>>();
+        let previous = vec![vec![0, 1]];
+        // both stay in the only row, which is the front and the back
+        let new = vec![vec![1, 0]];
+        let weights = Weights {
+            zone_repeat: 400.0,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            individual(&previous, &new, &students, &Weights::default()).unwrap(),
+            vec![1000, 1000]
+        );
+        assert_eq!(
+            individual(&previous, &new, &students, &weights).unwrap(),
+            vec![200, 200]
+        );
     }
 
-    Ok(individual_scores)
+    #[test]
+    fn exam_scores() {
+        let students = (0..4)
+            .map(|i| Student {
+                id: i,
+                name: format!("Student {}", i),
+                academic_ability: 3,
+                exercise_ability: 3,
+                leadership_ability: 3,
+                needs_assistance: false,
+                gender: Gender::Male,
+            })
+            .collect::<Vec<Student>>();
+        let previous = vec![vec![0, 1, 2, 3]];
+        let weights = Weights::default();
+        let score = |new: &SeatAssignment, versions| {
+            evaluate_exam(&previous, new, &students, &weights, versions).unwrap()
+        };
+
+        let close = vec![vec![0, 1], vec![2, 3]];
+        let apart = vec![vec![0, 2], vec![3, 1]];
+        assert_eq!(score(&close, TestVersions::Single), 1000);
+        assert_eq!(score(&apart, TestVersions::Single), 1500);
+
+        // no two students side by side or one behind the other write the same version
+        assert_eq!(score(&close, TestVersions::Checkerboard), 1000);
+        // 0 and 2 write version A and 1 and 3 version B
+        assert_eq!(score(&close, TestVersions::AttendanceOrder), -1000);
+        assert_eq!(score(&apart, TestVersions::AttendanceOrder), -500);
+
+        assert!(evaluate_exam(
+            &previous,
+            &vec![vec![0, 1]],
+            &students,
+            &weights,
+            TestVersions::Single
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn incremental_evaluator_matches_evaluate() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+
+        for (depth, width, vacancy_ratio) in [(5, 6, 0.0), (6, 6, 0.3), (3, 8, 0.1), (1, 4, 0.25)] {
+            let config = ScenarioConfig {
+                depth,
+                width,
+                vacancy_ratio,
+                ..Default::default()
+            };
+            let layout = scenario::generate(&config, &mut rng).unwrap();
+            let (mut previous, mut students) = separate_input(&layout);
+            compress_student_id(&mut students, &mut previous).unwrap();
+            let weights = Weights {
+                blackboard_distance: 700.0,
+                zone_repeat: 300.0,
+                ..Default::default()
+            };
+
+            let occupied = occupied_seats(&previous);
+            let context = EvaluationContext::new(&previous, &students, &weights).unwrap();
+            let mut evaluator = IncrementalEvaluator::new(&context, &previous).unwrap();
+            let mut new = previous.clone();
+            for _ in 0..200 {
+                let pair = index::sample(&mut rng, occupied.len(), 2);
+                let (pos1, pos2) = (occupied[pair.index(0)], occupied[pair.index(1)]);
+
+                evaluator.swap(pos1, pos2);
+                swap_seats(&mut new, pos1, pos2);
+
+                assert_eq!(evaluator.layout(), &new);
+                assert_eq!(
+                    evaluator.score().unwrap(),
+                    evaluate(&previous, &new, &students, &weights).unwrap()
+                );
+
+                if rng.gen_bool(0.3) {
+                    evaluator = IncrementalEvaluator::new(&context, &new).unwrap();
+                }
+            }
+        }
+    }
 }
@@ -0,0 +1,417 @@
+use crate::{
+    constraints::{compress_constraints, Rules},
+    eval_func::{
+        balance_score, blackboard_distance, evaluate, individual, prev_adj_students,
+        score_components,
+    },
+    rotation::{seat_zones, zone_counts, zone_repeats, Zone},
+    structs::{
+        Config, Constraint, Layout, ScoreComponent, ScoreComponents, SeatAssignment, Student,
+    },
+    utils::{check_input, compress_history, compress_student_id, separate_input, swap_seats},
+};
+
+use std::io::Error;
+
+/// Student who sat next to the explained student in some past layouts.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct PreviousNeighbour {
+    pub id: usize,
+    pub name: String,
+    /// Number of past layouts in which they sat next to each other.
+    pub layouts: usize,
+    /// Manhattan distance between their new seats.
+    pub distance: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ConstraintStatus {
+    pub constraint: Constraint,
+    pub satisfied: bool,
+}
+
+/// Seat the explained student could swap to. Rows and columns are counted from 1 as on the PDF.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SwapOption {
+    pub row: usize,
+    pub column: usize,
+    /// Id of the student sitting there, `None` for a vacant seat.
+    pub student: Option<usize>,
+    pub score_change: i64,
+    pub satisfies_constraints: bool,
+}
+
+/// Scores of a layout before and after swapping two seats.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SwapScore {
+    pub score: i64,
+    pub swapped_score: i64,
+    pub delta: i64,
+    pub before: ScoreComponents,
+    pub after: ScoreComponents,
+    pub changed: Vec<ScoreComponent>,
+    pub satisfies_constraints: bool,
+}
+
+/// Score terms of one student in a layout. The total score adds the mean of the individual
+/// scores over the class to the balance terms.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Explanation {
+    pub id: usize,
+    pub name: String,
+    pub row: usize,
+    pub column: usize,
+    pub previous_neighbours: Vec<PreviousNeighbour>,
+    /// Mean distance to the previous neighbours times its weight.
+    pub previous_neighbour_score: i64,
+    pub blackboard_distance: f64,
+    /// Penalty for the distance to the blackboard, 0 unless the student needs assistance.
+    pub assistance_score: i64,
+    pub zones: Vec<Zone>,
+    /// Penalty for the past layouts in which the student sat in the same zones.
+    pub rotation_score: i64,
+    /// How much lower the balance terms would be if the seat were vacant.
+    pub balance_contribution: i64,
+    /// Constraints on the student.
+    pub constraints: Vec<ConstraintStatus>,
+    /// Swaps with every other seat, best first.
+    pub swaps: Vec<SwapOption>,
+}
+
+/// Explains the seat of the student with `student_id` in `new`, given the past layouts of
+/// `history` with the most recent one first.
+pub fn explain(
+    history: &[Layout],
+    new: &[Vec<Option<Student>>],
+    student_id: usize,
+    config: &Config,
+) -> Result<Explanation, Error> {
+    let (layout, students, original_student_ids) = compress_layout(new)?;
+    let i = original_student_ids
+        .binary_search(&student_id)
+        .map_err(|_| {
+            Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Student {} is not in the layout", student_id),
+            )
+        })?;
+    let previous = compress_history(history, &original_student_ids)?;
+
+    let (depth, width, n) = (layout.len(), layout[0].len(), students.len());
+    let pos = position(&layout, i);
+    let weights = &config.weights;
+
+    let adjacent = &prev_adj_students(&previous, n)[i];
+    let mut previous_neighbours = vec![];
+    for &j in adjacent.iter() {
+        if previous_neighbours
+            .iter()
+            .any(|p: &PreviousNeighbour| p.id == original_student_ids[j])
+        {
+            continue;
+        }
+        let other = position(&layout, j);
+        previous_neighbours.push(PreviousNeighbour {
+            id: original_student_ids[j],
+            name: students[j].name.clone(),
+            layouts: adjacent.iter().filter(|&&k| k == j).count(),
+            distance: pos.0.abs_diff(other.0) + pos.1.abs_diff(other.1),
+        });
+    }
+    previous_neighbours.sort_by_key(|p| p.id);
+
+    let individual_score = individual(&previous, &layout, &students, weights)?[i];
+    let blackboard_distance = blackboard_distance(pos, width);
+    let assistance_score = if students[i].needs_assistance {
+        -((blackboard_distance * weights.blackboard_distance) as i64)
+    } else {
+        0
+    };
+    let rotation_score = -((zone_repeats(&zone_counts(&previous, n)[i], pos, depth, width) as f64
+        * weights.zone_repeat) as i64);
+
+    let mut vacated = layout.clone();
+    vacated[pos.1][pos.0] = !0;
+    // with nobody left there is nothing to balance
+    let balance_contribution = balance_score(&layout, &students, weights)?
+        - balance_score(&vacated, &students, weights).unwrap_or(0);
+
+    let compressed = compress_constraints(&config.constraints, &original_student_ids);
+    let constraints = config
+        .constraints
+        .iter()
+        .zip(compressed.iter())
+        .filter(|(constraint, _)| refers_to(constraint, student_id))
+        .map(|(constraint, compressed)| ConstraintStatus {
+            constraint: constraint.clone(),
+            satisfied: Rules::new(std::slice::from_ref(compressed), depth, width, n)
+                .satisfied_at(&layout, pos),
+        })
+        .collect();
+
+    let rules = Rules::new(&compressed, depth, width, n).with_spacing(config.spacing);
+    let score = evaluate(&previous, &layout, &students, weights)?;
+    let mut swaps = vec![];
+    for y in 0..depth {
+        for x in 0..width {
+            if (x, y) == pos {
+                continue;
+            }
+
+            let mut swapped = layout.clone();
+            swap_seats(&mut swapped, pos, (x, y));
+            let other = layout[y][x];
+            swaps.push(SwapOption {
+                row: y + 1,
+                column: x + 1,
+                student: (other != !0).then(|| original_student_ids[other]),
+                score_change: evaluate(&previous, &swapped, &students, weights)? - score,
+                satisfies_constraints: rules.satisfied_at(&swapped, pos)
+                    && rules.satisfied_at(&swapped, (x, y)),
+            });
+        }
+    }
+    swaps.sort_by_key(|swap| std::cmp::Reverse(swap.score_change));
+
+    Ok(Explanation {
+        id: student_id,
+        name: students[i].name.clone(),
+        row: pos.1 + 1,
+        column: pos.0 + 1,
+        previous_neighbours,
+        previous_neighbour_score: individual_score - assistance_score - rotation_score,
+        blackboard_distance,
+        assistance_score,
+        zones: seat_zones(pos, depth, width),
+        rotation_score,
+        balance_contribution,
+        constraints,
+        swaps,
+    })
+}
+
+/// Swaps the seats `a` and `b` of `layout`, given by the column `x` and the row `y`, and scores
+/// the result against the past layouts of `history`, the most recent one first.
+pub fn what_if_swap(
+    history: &[Layout],
+    layout: &[Vec<Option<Student>>],
+    a: (usize, usize),
+    b: (usize, usize),
+    config: &Config,
+) -> Result<SwapScore, Error> {
+    let (layout, students, original_student_ids) = compress_layout(layout)?;
+    let previous = compress_history(history, &original_student_ids)?;
+
+    let (depth, width) = (layout.len(), layout[0].len());
+    if [a, b].iter().any(|&(x, y)| x >= width || y >= depth) {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Seat out of the layout",
+        ));
+    }
+
+    let mut swapped = layout.clone();
+    swap_seats(&mut swapped, a, b);
+
+    let weights = &config.weights;
+    let (score, swapped_score) = (
+        evaluate(&previous, &layout, &students, weights)?,
+        evaluate(&previous, &swapped, &students, weights)?,
+    );
+    let (before, after) = (
+        score_components(&previous, &layout, &students, weights)?,
+        score_components(&previous, &swapped, &students, weights)?,
+    );
+
+    let rules = Rules::new(
+        &compress_constraints(&config.constraints, &original_student_ids),
+        depth,
+        width,
+        students.len(),
+    )
+    .with_spacing(config.spacing);
+
+    Ok(SwapScore {
+        score,
+        swapped_score,
+        delta: swapped_score - score,
+        changed: before.changed(&after),
+        before,
+        after,
+        satisfies_constraints: rules.satisfied_at(&swapped, a) && rules.satisfied_at(&swapped, b),
+    })
+}
+
+/// Students of `layout` with their ids replaced by their indices in the sorted original ids,
+/// which are returned as well.
+fn compress_layout(
+    layout: &[Vec<Option<Student>>],
+) -> Result<(SeatAssignment, Vec<Student>, Vec<usize>), Error> {
+    check_input(layout)?;
+
+    let (mut layout, mut students) = separate_input(layout);
+    let original_student_ids = students.iter().map(|s| s.id).collect::<Vec<usize>>();
+    compress_student_id(&mut students, &mut layout)?;
+
+    Ok((layout, students, original_student_ids))
+}
+
+fn position(layout: &SeatAssignment, student: usize) -> (usize, usize) {
+    for (y, row) in layout.iter().enumerate() {
+        for (x, &id) in row.iter().enumerate() {
+            if id == student {
+                return (x, y);
+            }
+        }
+    }
+
+    unreachable!("every student of the layout is seated")
+}
+
+fn refers_to(constraint: &Constraint, id: usize) -> bool {
+    match *constraint {
+        Constraint::Pin { student, .. } | Constraint::Zone { student, .. } => student == id,
+        Constraint::Separate { students } => students.contains(&id),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Gender, Weights};
+
+    use super::*;
+
+    fn student(id: usize, needs_assistance: bool) -> Option<Student> {
+        Some(Student {
+            id,
+            name: format!("Student {}", id),
+            academic_ability: id % 5 + 1,
+            exercise_ability: 3,
+            leadership_ability: 3,
+            needs_assistance,
+            gender: Gender::Male,
+        })
+    }
+
+    #[test]
+    fn explain_back_seat() {
+        let previous = vec![
+            vec![student(10, false), student(20, false), student(30, false)],
+            vec![student(40, true), None, None],
+        ];
+        let new = vec![
+            vec![student(10, false), None, student(20, false)],
+            vec![student(30, false), None, student(40, true)],
+        ];
+        let config = Config {
+            constraints: vec![
+                Constraint::Separate { students: [40, 10] },
+                Constraint::Pin {
+                    student: 20,
+                    x: 2,
+                    y: 0,
+                },
+            ],
+            weights: Weights {
+                zone_repeat: 500.0,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let explanation = explain(&[previous], &new, 40, &config).unwrap();
+        assert_eq!((explanation.row, explanation.column), (2, 3));
+        assert_eq!(
+            explanation.previous_neighbours,
+            vec![
+                PreviousNeighbour {
+                    id: 10,
+                    name: "Student 10".to_string(),
+                    layouts: 1,
+                    distance: 3,
+                },
+                PreviousNeighbour {
+                    id: 20,
+                    name: "Student 20".to_string(),
+                    layouts: 1,
+                    distance: 1,
+                },
+            ]
+        );
+        assert_eq!(explanation.previous_neighbour_score, 2000);
+        assert!(explanation.assistance_score < 0);
+        // 40 sat in the back row before, but not on the aisle
+        assert_eq!(explanation.zones, vec![Zone::Back, Zone::Aisle]);
+        assert_eq!(explanation.rotation_score, -500);
+        assert_eq!(
+            explanation.constraints,
+            vec![ConstraintStatus {
+                constraint: Constraint::Separate { students: [40, 10] },
+                satisfied: true,
+            }]
+        );
+
+        assert_eq!(explanation.swaps.len(), 5);
+        assert!(explanation
+            .swaps
+            .windows(2)
+            .all(|w| w[0].score_change >= w[1].score_change));
+        // 40 would sit next to 10
+        let swap = explanation
+            .swaps
+            .iter()
+            .find(|swap| (swap.row, swap.column) == (1, 2))
+            .unwrap();
+        assert_eq!(swap.student, None);
+        assert!(!swap.satisfies_constraints);
+
+        assert!(explain(&[], &new, 50, &config).is_err());
+    }
+
+    #[test]
+    fn swap_scores() {
+        let previous = vec![vec![
+            student(1, false),
+            student(2, false),
+            student(3, false),
+        ]];
+        let layout = vec![
+            vec![student(1, false), None, student(2, false)],
+            vec![student(3, true), None, None],
+        ];
+        let config = Config {
+            constraints: vec![Constraint::Separate { students: [1, 3] }],
+            ..Default::default()
+        };
+
+        let score = what_if_swap(
+            std::slice::from_ref(&previous),
+            &layout,
+            (0, 1),
+            (1, 1),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(score.delta, score.swapped_score - score.score);
+        let sum =
+            |c: &ScoreComponents| c.individual + c.academic + c.exercise + c.leadership + c.gender;
+        assert_eq!(sum(&score.before), score.score);
+        assert_eq!(sum(&score.after), score.swapped_score);
+        assert!(score.changed.contains(&ScoreComponent::Individual));
+        assert!(!score.satisfies_constraints);
+
+        // swapping a seat with itself changes nothing
+        let same = what_if_swap(
+            std::slice::from_ref(&previous),
+            &layout,
+            (2, 0),
+            (2, 0),
+            &config,
+        )
+        .unwrap();
+        assert_eq!(same.delta, 0);
+        assert!(same.changed.is_empty());
+
+        assert!(what_if_swap(&[previous], &layout, (3, 0), (0, 0), &config).is_err());
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    eval_func::test_version,
+    structs::{Student, TestVersions},
+    utils::check_input,
+};
+
+use std::{collections::HashMap, io::Error};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// Seat of one student. Rows and columns are counted from 1 as printed on the PDF: row 1 is the
+/// front row at the top of the page and column 1 is the leftmost column. The previous seat is
+/// empty for students who were not in the previous layout.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct SeatRecord {
+    pub id: usize,
+    pub name: String,
+    pub row: usize,
+    pub column: usize,
+    pub previous_row: Option<usize>,
+    pub previous_column: Option<usize>,
+}
+
+/// One record per student of `new`, sorted by id.
+pub fn seat_records(
+    previous: &[Vec<Option<Student>>],
+    new: &[Vec<Option<Student>>],
+) -> Result<Vec<SeatRecord>, Error> {
+    check_input(previous)?;
+    check_input(new)?;
+
+    let previous_seats = seats(previous)
+        .map(|(row, column, student)| (student.id, (row, column)))
+        .collect::<HashMap<usize, (usize, usize)>>();
+
+    let mut records = seats(new)
+        .map(|(row, column, student)| {
+            let previous_seat = previous_seats.get(&student.id);
+            SeatRecord {
+                id: student.id,
+                name: student.name.clone(),
+                row,
+                column,
+                previous_row: previous_seat.map(|&(row, _)| row),
+                previous_column: previous_seat.map(|&(_, column)| column),
+            }
+        })
+        .collect::<Vec<SeatRecord>>();
+    records.sort_by_key(|record| record.id);
+
+    Ok(records)
+}
+
+pub(crate) fn seats(
+    layout: &[Vec<Option<Student>>],
+) -> impl Iterator<Item = (usize, usize, &Student)> {
+    layout.iter().enumerate().flat_map(|(y, row)| {
+        row.iter()
+            .enumerate()
+            .filter_map(move |(x, seat)| seat.as_ref().map(|student| (y + 1, x + 1, student)))
+    })
+}
+
+/// Text of each seat of `layout` on the PDF: the id and the name of the student, followed by the
+/// version of the test for exams with several versions. Vacant seats are empty.
+pub fn seat_labels(layout: &[Vec<Option<Student>>], versions: TestVersions) -> Vec<Vec<String>> {
+    let mut ids = seats(layout)
+        .map(|(_, _, student)| student.id)
+        .collect::<Vec<usize>>();
+    ids.sort();
+
+    layout
+        .iter()
+        .enumerate()
+        .map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .map(|(x, seat)| {
+                    let Some(student) = seat else {
+                        return "".to_string();
+                    };
+
+                    let rank = ids.partition_point(|&id| id < student.id);
+                    match test_version(versions, (x, y), rank) {
+                        Some(version) => {
+                            format!("{}. {} [{}]", student.id, student.name, ['A', 'B'][version])
+                        }
+                        None => format!("{}. {}", student.id, student.name),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Seat records of `new` as CSV with a header row, or as a JSON list.
+pub fn export(
+    previous: &[Vec<Option<Student>>],
+    new: &[Vec<Option<Student>>],
+    format: ExportFormat,
+) -> Result<String, Error> {
+    let records = seat_records(previous, new)?;
+
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for record in records.iter() {
+                writer.serialize(record)?;
+            }
+            let bytes = writer
+                .into_inner()
+                .map_err(|err| Error::other(err.to_string()))?;
+
+            String::from_utf8(bytes).map_err(Error::other)
+        }
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(&records)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::Gender;
+
+    use super::*;
+
+    fn student(id: usize, name: &str) -> Option<Student> {
+        Some(Student {
+            id,
+            name: name.to_string(),
+            academic_ability: 3,
+            exercise_ability: 3,
+            leadership_ability: 3,
+            needs_assistance: false,
+            gender: Gender::Male,
+        })
+    }
+
+    #[test]
+    fn records_of_moved_and_new_students() {
+        let previous = vec![vec![student(2, "B"), student(1, "A")], vec![None, None]];
+        let new = vec![
+            vec![None, student(2, "B")],
+            vec![student(3, "C"), student(1, "A")],
+        ];
+
+        let records = seat_records(&previous, &new).unwrap();
+        assert_eq!(
+            records
+                .iter()
+                .map(|r| (r.id, r.row, r.column, r.previous_row, r.previous_column))
+                .collect::<Vec<_>>(),
+            vec![
+                (1, 2, 2, Some(1), Some(2)),
+                (2, 1, 2, Some(1), Some(1)),
+                (3, 2, 1, None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn export_formats() {
+        let previous = vec![vec![student(1, "山田, 太郎"), None]];
+        let new = vec![vec![student(2, "B"), student(1, "山田, 太郎")]];
+
+        let csv = export(&previous, &new, ExportFormat::Csv).unwrap();
+        assert_eq!(
+            csv,
+            "id,name,row,column,previous_row,previous_column\n\
+             1,\"山田, 太郎\",1,2,1,1\n\
+             2,B,1,1,,\n"
+        );
+
+        let json = export(&previous, &new, ExportFormat::Json).unwrap();
+        let records = serde_json::from_str::<Vec<SeatRecord>>(&json).unwrap();
+        assert_eq!(records, seat_records(&previous, &new).unwrap());
+
+        assert!(export(&previous, &[], ExportFormat::Json).is_err());
+    }
+
+    #[test]
+    fn labels_with_test_versions() {
+        let layout = vec![
+            vec![student(5, "A"), student(2, "B")],
+            vec![None, student(9, "C")],
+        ];
+
+        assert_eq!(
+            seat_labels(&layout, TestVersions::Single),
+            vec![vec!["5. A", "2. B"], vec!["", "9. C"]]
+        );
+        assert_eq!(
+            seat_labels(&layout, TestVersions::Checkerboard),
+            vec![vec!["5. A [A]", "2. B [B]"], vec!["", "9. C [A]"]]
+        );
+        // 2, 5 and 9 are the first, second and third in attendance order
+        assert_eq!(
+            seat_labels(&layout, TestVersions::AttendanceOrder),
+            vec![vec!["5. A [B]", "2. B [A]"], vec!["", "9. C [A]"]]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use crate::structs::{Gender, Student};
+
+use std::{collections::HashSet, io::Error};
+
+/// Columns of a roster file, counted from 0. Students take the default value of the
+/// `EditLayout` form for unmapped columns and empty cells.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ColumnMapping {
+    pub id: usize,
+    pub name: usize,
+    pub academic_ability: Option<usize>,
+    pub exercise_ability: Option<usize>,
+    pub leadership_ability: Option<usize>,
+    pub gender: Option<usize>,
+    pub needs_assistance: Option<usize>,
+    /// Skip the first row.
+    pub has_header: bool,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        ColumnMapping {
+            id: 0,
+            name: 1,
+            academic_ability: Some(2),
+            exercise_ability: Some(3),
+            leadership_ability: Some(4),
+            gender: Some(5),
+            needs_assistance: Some(6),
+            has_header: true,
+        }
+    }
+}
+
+/// Problem with one row of a roster file. `line` is counted from 1 and includes the header.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RowError {
+    pub line: u64,
+    pub message: String,
+}
+
+/// Students of the valid rows and the problems with the other rows.
+#[derive(Clone, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Roster {
+    pub students: Vec<Student>,
+    pub errors: Vec<RowError>,
+}
+
+const DEFAULT_ABILITY: usize = 3;
+const ABILITY_RANGE: std::ops::RangeInclusive<usize> = 1..=5;
+
+/// Reads students from a CSV file encoded in UTF-8 or Shift_JIS, which is what Excel uses for
+/// Japanese. Blank rows are skipped.
+pub fn import_csv(bytes: &[u8], mapping: &ColumnMapping) -> Result<Roster, Error> {
+    let text = decode(bytes)?;
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(mapping.has_header)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+
+    let mut roster = Roster::default();
+    let mut ids = HashSet::new();
+    for record in reader.records() {
+        let record = match record {
+            Ok(record) => record,
+            Err(err) => {
+                roster.errors.push(RowError {
+                    line: err.position().map_or(0, |p| p.line()),
+                    message: err.to_string(),
+                });
+                continue;
+            }
+        };
+        if record.iter().all(|cell| cell.trim().is_empty()) {
+            continue;
+        }
+
+        let line = record.position().map_or(0, |p| p.line());
+        match parse_student(&record, mapping) {
+            Ok(student) if !ids.insert(student.id) => roster.errors.push(RowError {
+                line,
+                message: format!("Duplicated student id {}", student.id),
+            }),
+            Ok(student) => roster.students.push(student),
+            Err(message) => roster.errors.push(RowError { line, message }),
+        }
+    }
+
+    Ok(roster)
+}
+
+fn decode(bytes: &[u8]) -> Result<String, Error> {
+    let bytes = bytes.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(bytes);
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        return Ok(text.to_string());
+    }
+
+    let (text, had_errors) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(bytes);
+    if had_errors {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidData,
+            "File is neither UTF-8 nor Shift_JIS",
+        ));
+    }
+
+    Ok(text.into_owned())
+}
+
+fn parse_student(record: &csv::StringRecord, mapping: &ColumnMapping) -> Result<Student, String> {
+    let cell = |column: usize, field: &str| {
+        record
+            .get(column)
+            .map(str::trim)
+            .ok_or_else(|| format!("Column {} for {} is missing", column + 1, field))
+    };
+    let optional_cell = |column: Option<usize>, field: &str| match column {
+        Some(column) => cell(column, field).map(|value| Some(value).filter(|v| !v.is_empty())),
+        None => Ok(None),
+    };
+    let ability = |column: Option<usize>, field: &str| match optional_cell(column, field)? {
+        Some(value) => value
+            .parse::<usize>()
+            .ok()
+            .filter(|ability| ABILITY_RANGE.contains(ability))
+            .ok_or_else(|| {
+                format!(
+                    "{} must be an integer from {} to {}, got \"{}\"",
+                    field,
+                    ABILITY_RANGE.start(),
+                    ABILITY_RANGE.end(),
+                    value
+                )
+            }),
+        None => Ok(DEFAULT_ABILITY),
+    };
+
+    let id = cell(mapping.id, "id")?;
+    let id = id
+        .parse::<usize>()
+        .map_err(|_| format!("id must be a non-negative integer, got \"{}\"", id))?;
+
+    let name = cell(mapping.name, "name")?;
+    if name.is_empty() {
+        return Err("name is empty".to_string());
+    }
+
+    let gender = match optional_cell(mapping.gender, "gender")? {
+        Some(value) => parse_gender(value)
+            .ok_or_else(|| format!("gender must be male or female, got \"{}\"", value))?,
+        None => Gender::Male,
+    };
+
+    let needs_assistance = match optional_cell(mapping.needs_assistance, "needs_assistance")? {
+        Some(value) => parse_bool(value)
+            .ok_or_else(|| format!("needs_assistance must be yes or no, got \"{}\"", value))?,
+        None => false,
+    };
+
+    Ok(Student {
+        id,
+        name: name.to_string(),
+        academic_ability: ability(mapping.academic_ability, "academic_ability")?,
+        exercise_ability: ability(mapping.exercise_ability, "exercise_ability")?,
+        leadership_ability: ability(mapping.leadership_ability, "leadership_ability")?,
+        needs_assistance,
+        gender,
+    })
+}
+
+fn parse_gender(value: &str) -> Option<Gender> {
+    match value.to_lowercase().as_str() {
+        "male" | "m" | "男" | "男子" => Some(Gender::Male),
+        "female" | "f" | "女" | "女子" => Some(Gender::Female),
+        _ => None,
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "y" | "1" | "○" | "はい" | "要" => Some(true),
+        "false" | "no" | "n" | "0" | "×" | "いいえ" | "否" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CSV: &str = "番号,名前,学力,運動,リーダー,性別,要支援
+1,山田 太郎,4,3,2,男,はい
+2,佐藤 花子,,5,3,女,
+";
+
+    #[test]
+    fn import_with_default_mapping() {
+        let roster = import_csv(CSV.as_bytes(), &ColumnMapping::default()).unwrap();
+
+        assert!(roster.errors.is_empty());
+        assert_eq!(
+            roster.students,
+            vec![
+                Student {
+                    id: 1,
+                    name: "山田 太郎".to_string(),
+                    academic_ability: 4,
+                    exercise_ability: 3,
+                    leadership_ability: 2,
+                    needs_assistance: true,
+                    gender: Gender::Male,
+                },
+                Student {
+                    id: 2,
+                    name: "佐藤 花子".to_string(),
+                    academic_ability: 3,
+                    exercise_ability: 5,
+                    leadership_ability: 3,
+                    needs_assistance: false,
+                    gender: Gender::Female,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn import_shift_jis() {
+        let (bytes, _, had_errors) = encoding_rs::SHIFT_JIS.encode(CSV);
+        assert!(!had_errors);
+
+        let roster = import_csv(&bytes, &ColumnMapping::default()).unwrap();
+        assert!(roster.errors.is_empty());
+        assert_eq!(roster.students[0].name, "山田 太郎");
+        assert_eq!(roster.students[1].gender, Gender::Female);
+
+        // a BOM marks UTF-8 as written by Excel
+        let with_bom = [b"\xEF\xBB\xBF".as_slice(), CSV.as_bytes()].concat();
+        assert_eq!(
+            import_csv(&with_bom, &ColumnMapping::default()).unwrap(),
+            roster
+        );
+
+        assert!(import_csv(&[0x82, 0xff, 0xff], &ColumnMapping::default()).is_err());
+    }
+
+    #[test]
+    fn import_with_custom_mapping() {
+        let csv = "Taro,F,12\n\nHanako,m,13\n";
+        let mapping = ColumnMapping {
+            id: 2,
+            name: 0,
+            academic_ability: None,
+            exercise_ability: None,
+            leadership_ability: None,
+            gender: Some(1),
+            needs_assistance: None,
+            has_header: false,
+        };
+
+        let roster = import_csv(csv.as_bytes(), &mapping).unwrap();
+        assert!(roster.errors.is_empty());
+        assert_eq!(
+            roster
+                .students
+                .iter()
+                .map(|s| (s.id, s.gender))
+                .collect::<Vec<(usize, Gender)>>(),
+            vec![(12, Gender::Female), (13, Gender::Male)]
+        );
+    }
+
+    #[test]
+    fn row_errors() {
+        let csv = "id,name,academic,exercise,leadership,gender,assistance
+1,Taro,3,3,3,male,no
+x,Jiro,3,3,3,male,no
+3,,3,3,3,male,no
+4,Saburo,6,3,3,male,no
+5,Shiro,3,3,3,other,no
+6,Goro,3,3,3,male,maybe
+1,Rokuro,3,3,3,male,no
+8,Shichiro
+";
+
+        let roster = import_csv(csv.as_bytes(), &ColumnMapping::default()).unwrap();
+        assert_eq!(roster.students.len(), 1);
+        assert_eq!(
+            roster.errors.iter().map(|e| e.line).collect::<Vec<u64>>(),
+            vec![3, 4, 5, 6, 7, 8, 9]
+        );
+        assert!(roster.errors[0].message.contains("id"));
+        assert!(roster.errors[2].message.contains("academic_ability"));
+        assert!(roster.errors[5].message.contains("Duplicated"));
+        assert!(roster.errors[6].message.contains("missing"));
+    }
+}
@@ -1,108 +1,424 @@
 #![feature(test)]
 extern crate test;
 
+pub mod batch;
 mod beam_search;
+mod branch_and_bound;
+pub mod constraints;
+pub mod diff;
 mod eval_func;
+pub mod explain;
+pub mod export;
+pub mod import;
+mod moves;
+pub mod partition;
+pub mod project;
+pub mod room;
+pub mod rotation;
+pub mod scenario;
 mod simulated_annealing;
 pub mod structs;
 mod tabu_search;
+pub mod tuning;
 mod utils;
 
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
 use std::hash::{Hash, Hasher};
-use std::{
-    collections::{hash_map::DefaultHasher, BinaryHeap, HashSet, VecDeque},
-    io::Error,
-};
+use std::{collections::hash_map::DefaultHasher, io::Error};
 
-use structs::{SeatAssignment, Student};
-use utils::{check_input, compress_student_id, separate_input};
+use constraints::{
+    check_feasibility, compress_constraints, conflict_error, feasibility, Feasibility,
+};
+use moves::occupied_seats;
+use structs::{
+    Algorithm, Config, Constraint, Layout, MoveStats, Objective, PastLayouts, Room, SeatAssignment,
+    Spacing, Student, Weights,
+};
+use utils::{
+    check_input, check_unique_ids, compress_history, compress_student_id, initial_layout,
+    room_seats, separate_input,
+};
 
 pub fn solve(
     previous: &SeatAssignment,
     students: &[Student],
 ) -> Result<(SeatAssignment, i64), Error> {
-    let seed = {
-        let seed_base_str = format!(
-            "{}{}",
-            serde_json::to_string(students).unwrap(),
-            serde_json::to_string(previous).unwrap()
-        );
+    solve_with_config(previous, students, &Config::default())
+}
+
+pub fn solve_with_config(
+    previous: &SeatAssignment,
+    students: &[Student],
+    config: &Config,
+) -> Result<(SeatAssignment, i64), Error> {
+    let (new, score, _) = solve_with_stats(previous, students, config)?;
+    Ok((new, score))
+}
 
-        let mut s = DefaultHasher::new();
-        seed_base_str.hash(&mut s);
-        s.finish()
+/// Same as `solve_with_config`, but also returns how often each move of simulated annealing
+/// was proposed and accepted. The statistics are empty for beam search and tabu search.
+pub fn solve_with_stats(
+    previous: &SeatAssignment,
+    students: &[Student],
+    config: &Config,
+) -> Result<(SeatAssignment, i64, MoveStats), Error> {
+    let seed = match config.seed {
+        Some(seed) => seed,
+        None => input_seed(&[
+            serde_json::to_string(students)?,
+            serde_json::to_string(previous)?,
+        ]),
+    };
+
+    solve_on_seats(
+        previous,
+        previous,
+        &occupied_seats(previous),
+        students,
+        config,
+        seed,
+    )
+}
+
+/// Same as `solve_with_stats`, but seats the students in `room` instead of on the occupied
+/// seats of `previous`. The previous layouts may have a different size than `room` and may leave
+/// out students, who then have no previous neighbours. Students start from their seat in the
+/// most recent layout.
+pub fn solve_in_room(
+    previous: &dyn PastLayouts,
+    room: &Room,
+    students: &[Student],
+    config: &Config,
+) -> Result<(SeatAssignment, i64, MoveStats), Error> {
+    let seed = match config.seed {
+        Some(seed) => seed,
+        None => input_seed(&[
+            serde_json::to_string(students)?,
+            serde_json::to_string(previous.layouts())?,
+            serde_json::to_string(room)?,
+        ]),
     };
 
+    let latest = previous
+        .layouts()
+        .first()
+        .map_or(&[][..], |layout| &layout[..]);
+    let new = initial_layout(latest, room, students.len())?;
+    solve_on_seats(previous, &new, &room_seats(room), students, config, seed)
+}
+
+/// Seed derived from `parts`. Each part is hashed with its length, so the parts cannot run into
+/// each other.
+pub(crate) fn input_seed(parts: &[String]) -> u64 {
+    let mut s = DefaultHasher::new();
+    parts.hash(&mut s);
+    s.finish()
+}
+
+/// Moves the students of `new` between `seats`.
+fn solve_on_seats(
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    seats: &[(usize, usize)],
+    students: &[Student],
+    config: &Config,
+    seed: u64,
+) -> Result<(SeatAssignment, i64, MoveStats), Error> {
     let mut rng = ChaCha20Rng::seed_from_u64(seed);
 
-    simulated_annealing::execute(previous, students, LOOP_CNT, &mut rng, T1, T2)
+    if !config.constraints.is_empty()
+        && matches!(
+            config.algorithm,
+            Algorithm::BeamSearch | Algorithm::TabuSearch
+        )
+    {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Constraints are only supported by simulated annealing and branch and bound",
+        ));
+    }
+    if config.objective != Objective::Regular && config.algorithm != Algorithm::SimulatedAnnealing {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Exam mode is only supported by simulated annealing",
+        ));
+    }
+    if config.spacing != Spacing::Any && config.algorithm != Algorithm::SimulatedAnnealing {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Spacing is only supported by simulated annealing",
+        ));
+    }
+
+    let (new, score) = match config.algorithm {
+        Algorithm::SimulatedAnnealing => {
+            return simulated_annealing::execute(previous, new, seats, students, config, &mut rng)
+        }
+        Algorithm::BeamSearch => beam_search::beam_search(previous, new, seats, students, config)?,
+        Algorithm::BranchAndBound => {
+            return branch_and_bound::execute(previous, new, seats, students, config, &mut rng)
+        }
+        Algorithm::TabuSearch => {
+            tabu_search::execute(previous, new, seats, students, config, &mut rng)?
+        }
+    };
+
+    Ok((new, score, MoveStats::new()))
+}
+
+pub fn execute(current_layout: &[Vec<Option<Student>>]) -> Result<(Layout, i64), Error> {
+    execute_with_config(current_layout, &Config::default())
 }
 
-pub fn execute(
+pub fn execute_with_config(
     current_layout: &[Vec<Option<Student>>],
-) -> Result<(Vec<Vec<Option<Student>>>, i64), Error> {
-    let check_res = check_input(current_layout);
-    if check_res.is_err() {
-        return Err(check_res.err().unwrap());
-    }
+    config: &Config,
+) -> Result<(Layout, i64), Error> {
+    check_input(current_layout)?;
 
     let (mut previous, mut students) = separate_input(current_layout);
     let original_student_ids = students.iter().map(|s| s.id).collect::<Vec<usize>>();
 
-    compress_student_id(&mut students, &mut previous);
+    compress_student_id(&mut students, &mut previous)?;
 
-    let solve_result = solve(&previous, &students);
+    if let Feasibility::Infeasible { conflicts } =
+        check_feasibility(current_layout, &config.constraints)?
+    {
+        return Err(conflict_error(&conflicts));
+    }
+    let config = Config {
+        constraints: compress_constraints(&config.constraints, &original_student_ids),
+        ..config.clone()
+    };
+
+    let (new, score) = solve_with_config(&previous, &students, &config)?;
+
+    Ok((
+        restore_students(&new, &students, &original_student_ids)?,
+        score,
+    ))
+}
+
+/// Seats the students of `roster` in `room`. Students of `previous_layout` who are not in the
+/// roster any more are ignored, and students of the roster who are not in `previous_layout`
+/// are seated without history.
+pub fn execute_with_roster(
+    previous_layout: &[Vec<Option<Student>>],
+    roster: &[Student],
+    room: &Room,
+    config: &Config,
+) -> Result<(Layout, i64), Error> {
+    execute_with_history(&[previous_layout.to_vec()], roster, room, config)
+}
+
+/// Same as `execute_with_roster`, but moves apart the students who sat next to each other in
+/// any layout of `history`, the most recent one first.
+pub fn execute_with_history(
+    history: &[Layout],
+    roster: &[Student],
+    room: &Room,
+    config: &Config,
+) -> Result<(Layout, i64), Error> {
+    let mut students = roster.to_vec();
+    students.sort_by_key(|s| s.id);
+    let original_student_ids = students.iter().map(|s| s.id).collect::<Vec<usize>>();
+    check_unique_ids(&original_student_ids)?;
 
-    if solve_result.is_err() {
-        return Err(solve_result.err().unwrap());
+    let previous = compress_history(history, &original_student_ids)?;
+    for (i, student) in students.iter_mut().enumerate() {
+        student.id = i;
     }
 
-    let &score = &solve_result.as_ref().unwrap().1;
+    let latest = previous.first().map_or(&[][..], |layout| &layout[..]);
+    let new = initial_layout(latest, room, students.len())?;
+    if let Feasibility::Infeasible { conflicts } = feasibility(
+        &new,
+        &room_seats(room),
+        &config.constraints,
+        &original_student_ids,
+    ) {
+        return Err(conflict_error(&conflicts));
+    }
+    let config = Config {
+        constraints: compress_constraints(&config.constraints, &original_student_ids),
+        ..config.clone()
+    };
 
-    let mut res = solve_result
-        .unwrap()
-        .0
-        .iter()
-        .map(|row| {
-            row.iter()
-                .map(|&idx| {
-                    if idx == !0 {
-                        None
-                    } else {
-                        Some(students[idx].clone())
-                    }
-                })
-                .collect::<Vec<Option<Student>>>()
-        })
-        .collect::<Vec<Vec<Option<Student>>>>();
-
-    for y in 0..res.len() {
-        for x in 0..res[y].len() {
-            if res[y][x].is_some() {
-                res[y][x].as_mut().unwrap().id =
-                    original_student_ids[res[y][x].as_ref().unwrap().id];
+    let (new, score, _) = solve_in_room(&previous, room, &students, &config)?;
+
+    Ok((
+        restore_students(&new, &students, &original_student_ids)?,
+        score,
+    ))
+}
+
+/// Seats the students of `roster` in the empty `room`, e.g. for the first layout of the school
+/// year. Without a previous layout only the balance and assistance terms are optimised.
+pub fn execute_from_roster(
+    roster: &[Student],
+    room: &Room,
+    config: &Config,
+) -> Result<(Layout, i64), Error> {
+    let config = Config {
+        weights: Weights {
+            prev_adj_distance: 0.0,
+            ..config.weights
+        },
+        ..config.clone()
+    };
+
+    execute_with_history(&[], roster, room, &config)
+}
+
+/// Ratio of the starting temperature of `repair` to the final one, low enough that the layout
+/// is refined rather than shuffled.
+const REPAIR_TEMPERATURE_RATIO: f64 = 4.0;
+
+/// Re-optimises `layout` after manual edits, keeping the students on the seats of `fixed`, given
+/// by the column `x` and the row `y`. Simulated annealing starts from `layout` at a low
+/// temperature, so the students who are not fixed move around them without starting from
+/// scratch. `history` holds the layouts before `layout`, the most recent one first.
+pub fn repair(
+    history: &[Layout],
+    layout: &[Vec<Option<Student>>],
+    fixed: &[(usize, usize)],
+    config: &Config,
+) -> Result<(Layout, i64), Error> {
+    check_input(layout)?;
+
+    let (mut new, mut students) = separate_input(layout);
+    let original_student_ids = students.iter().map(|s| s.id).collect::<Vec<usize>>();
+    compress_student_id(&mut students, &mut new)?;
+    let previous = compress_history(history, &original_student_ids)?;
+
+    let mut constraints = config.constraints.clone();
+    for &(x, y) in fixed.iter() {
+        match layout.get(y).and_then(|row| row.get(x)) {
+            Some(Some(student)) => constraints.push(Constraint::Pin {
+                student: student.id,
+                x,
+                y,
+            }),
+            // students only move between occupied seats, so vacant seats stay vacant
+            Some(None) => {}
+            None => {
+                return Err(Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("Fixed seat ({}, {}) is out of the layout", x, y),
+                ))
             }
         }
     }
 
-    Ok((res, score))
+    let seats = occupied_seats(&new);
+    if let Feasibility::Infeasible { conflicts } =
+        feasibility(&new, &seats, &constraints, &original_student_ids)
+    {
+        return Err(conflict_error(&conflicts));
+    }
+    let config = Config {
+        algorithm: Algorithm::SimulatedAnnealing,
+        temperature1: config.temperature2 * REPAIR_TEMPERATURE_RATIO,
+        auto_temperature: false,
+        constraints: compress_constraints(&constraints, &original_student_ids),
+        ..config.clone()
+    };
+    let seed = match config.seed {
+        Some(seed) => seed,
+        None => input_seed(&[
+            serde_json::to_string(&students)?,
+            serde_json::to_string(&previous)?,
+            serde_json::to_string(&new)?,
+        ]),
+    };
+
+    let (new, score, _) = solve_on_seats(&previous, &new, &seats, &students, &config, seed)?;
+
+    Ok((
+        restore_students(&new, &students, &original_student_ids)?,
+        score,
+    ))
 }
 
-const LOOP_CNT: usize = 200000;
-const T1: f64 = 119.5;
-const T2: f64 = 1.563;
+/// Replaces the indices of `new` by the students with their original ids.
+fn restore_students(
+    new: &SeatAssignment,
+    students: &[Student],
+    original_student_ids: &[usize],
+) -> Result<Layout, Error> {
+    let mut res = vec![];
+    for row in new.iter() {
+        let mut res_row = vec![];
+        for &idx in row.iter() {
+            if idx == !0 {
+                res_row.push(None);
+                continue;
+            }
+
+            let mut student = students.get(idx).cloned().ok_or_else(|| {
+                Error::other(format!("Solver returned unknown student index {}", idx))
+            })?;
+            student.id = original_student_ids[idx];
+            res_row.push(Some(student));
+        }
+        res.push(res_row);
+    }
+
+    Ok(res)
+}
 
 #[cfg(test)]
 mod tests {
+    use rand::Rng;
+
     use crate::{
-        structs::{Gender, Student},
+        beam_search::beam_search,
+        eval_func::{evaluate, evaluate_exam},
+        scenario,
+        structs::{Gender, Student, TestVersions},
         utils::swap_seats,
     };
 
     use super::*;
 
+    fn random_student(rng: &mut ChaCha20Rng, id: usize) -> Student {
+        let mut ability = || {
+            if rng.gen_bool(0.05) {
+                usize::MAX
+            } else {
+                rng.gen_range(0..=6)
+            }
+        };
+
+        Student {
+            id,
+            name: format!("Student {}", id),
+            academic_ability: ability(),
+            exercise_ability: ability(),
+            leadership_ability: ability(),
+            needs_assistance: rng.gen_bool(0.2),
+            gender: if rng.gen_bool(0.5) {
+                Gender::Male
+            } else {
+                Gender::Female
+            },
+        }
+    }
+
+    fn random_shape(rng: &mut ChaCha20Rng) -> Vec<usize> {
+        let (depth, width) = (rng.gen_range(0..=4), rng.gen_range(0..=4));
+        (0..depth)
+            .map(|_| {
+                if rng.gen_bool(0.1) {
+                    rng.gen_range(0..=4)
+                } else {
+                    width
+                }
+            })
+            .collect()
+    }
+
     #[test]
     fn check_ignoreing_vacant() {
         let students = (0..24)
@@ -130,4 +446,311 @@ mod tests {
             swap_seats(&mut seat_assignment, (x, y), (4, 4));
         }
     }
+
+    #[test]
+    fn execute_rejects_malformed_layouts() {
+        let student = |id| Student {
+            id,
+            name: format!("Student {}", id),
+            academic_ability: 3,
+            exercise_ability: 3,
+            leadership_ability: 3,
+            needs_assistance: false,
+            gender: Gender::Male,
+        };
+
+        assert!(execute(&[]).is_err());
+        assert!(execute(&[vec![]]).is_err());
+        assert!(execute(&[vec![None, None]]).is_err());
+        assert!(execute(&[vec![Some(student(1)), None], vec![Some(student(2))]]).is_err());
+        assert!(execute(&[vec![Some(student(1)), Some(student(1))]]).is_err());
+    }
+
+    #[test]
+    fn repair_keeps_fixed_seats() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let previous_layout = scenario::generate(&Default::default(), &mut rng).unwrap();
+        let (solved, _) = execute(&previous_layout).unwrap();
+
+        // the teacher swaps two students and fixes them
+        let (a, b) = (occupied(&solved)[0], occupied(&solved)[5]);
+        let mut edited = solved.clone();
+        let student = edited[a.1][a.0].take();
+        edited[a.1][a.0] = edited[b.1][b.0].take();
+        edited[b.1][b.0] = student;
+
+        let config = Config {
+            loop_cnt: 20000,
+            ..Default::default()
+        };
+        let (repaired, _) = repair(
+            std::slice::from_ref(&previous_layout),
+            &edited,
+            &[a, b],
+            &config,
+        )
+        .unwrap();
+        assert_eq!(repaired[a.1][a.0], edited[a.1][a.0]);
+        assert_eq!(repaired[b.1][b.0], edited[b.1][b.0]);
+        assert_eq!(occupied(&repaired), occupied(&edited));
+
+        assert!(repair(&[previous_layout], &edited, &[(100, 0)], &config).is_err());
+    }
+
+    #[test]
+    fn exam_mode_separates_neighbours() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let layout = scenario::generate(&Default::default(), &mut rng).unwrap();
+        let (mut previous, mut students) = separate_input(&layout);
+        compress_student_id(&mut students, &mut previous).unwrap();
+        let exam_score = |new: &SeatAssignment| {
+            evaluate_exam(
+                &previous,
+                new,
+                &students,
+                &Weights::default(),
+                TestVersions::AttendanceOrder,
+            )
+            .unwrap()
+        };
+
+        let regular = Config {
+            loop_cnt: 20000,
+            ..Default::default()
+        };
+        let exam = Config {
+            objective: Objective::Exam {
+                versions: TestVersions::AttendanceOrder,
+            },
+            ..regular.clone()
+        };
+        let (regular_layout, _) = solve_with_config(&previous, &students, &regular).unwrap();
+        let (exam_layout, score) = solve_with_config(&previous, &students, &exam).unwrap();
+        assert_eq!(score, exam_score(&exam_layout));
+        assert!(score > exam_score(&regular_layout));
+
+        let beam_search = Config {
+            algorithm: Algorithm::BeamSearch,
+            ..exam
+        };
+        assert!(solve_with_config(&previous, &students, &beam_search).is_err());
+    }
+
+    fn occupied(layout: &[Vec<Option<Student>>]) -> Vec<(usize, usize)> {
+        let (layout, _) = separate_input(layout);
+        occupied_seats(&layout)
+    }
+
+    #[test]
+    fn roster_with_newcomers_and_departures() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let previous_layout = scenario::generate(&Default::default(), &mut rng).unwrap();
+        let (_, previous_students) = separate_input(&previous_layout);
+
+        // three students left, two joined and a column of desks was added
+        let mut roster = previous_students[3..].to_vec();
+        roster.push(random_student(&mut rng, 1000));
+        roster.push(random_student(&mut rng, 1001));
+        let room = vec![vec![true; previous_layout[0].len() + 1]; previous_layout.len()];
+
+        for algorithm in [
+            Algorithm::SimulatedAnnealing,
+            Algorithm::BeamSearch,
+            Algorithm::TabuSearch,
+            Algorithm::BranchAndBound,
+        ] {
+            let config = Config {
+                algorithm,
+                loop_cnt: 10000,
+                ..Default::default()
+            };
+            let (new_layout, _) =
+                execute_with_roster(&previous_layout, &roster, &room, &config).unwrap();
+
+            assert_eq!(new_layout.len(), room.len());
+            assert!(new_layout.iter().all(|row| row.len() == room[0].len()));
+            let mut ids = new_layout
+                .iter()
+                .flatten()
+                .flatten()
+                .map(|s| s.id)
+                .collect::<Vec<usize>>();
+            ids.sort();
+            let mut want = roster.iter().map(|s| s.id).collect::<Vec<usize>>();
+            want.sort();
+            assert_eq!(ids, want);
+        }
+
+        let small_room = vec![vec![true; 2]; 2];
+        assert!(
+            execute_with_roster(&previous_layout, &roster, &small_room, &Config::default())
+                .is_err()
+        );
+        let duplicated = vec![roster[0].clone(), roster[0].clone()];
+        assert!(
+            execute_with_roster(&previous_layout, &duplicated, &room, &Config::default()).is_err()
+        );
+    }
+
+    #[test]
+    fn roster_with_vacant_seats_between() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let roster = (0..15)
+            .map(|i| random_student(&mut rng, i))
+            .collect::<Vec<Student>>();
+        let room = vec![vec![true; 6]; 5];
+        let config = Config {
+            loop_cnt: 20000,
+            spacing: Spacing::Horizontal,
+            ..Default::default()
+        };
+
+        let (new_layout, _) = execute_from_roster(&roster, &room, &config).unwrap();
+        assert_eq!(new_layout.iter().flatten().flatten().count(), 15);
+        for row in new_layout.iter() {
+            assert!(row
+                .windows(2)
+                .all(|seats| seats[0].is_none() || seats[1].is_none()));
+        }
+
+        // the students who stay sit next to each other at the back of the row
+        let previous_layout = vec![(1..=5)
+            .map(|id| Some(random_student(&mut rng, id)))
+            .collect::<Vec<Option<Student>>>()];
+        let staying = vec![
+            previous_layout[0][0].clone().unwrap(),
+            previous_layout[0][3].clone().unwrap(),
+            previous_layout[0][4].clone().unwrap(),
+        ];
+        let (new_layout, _) =
+            execute_with_roster(&previous_layout, &staying, &vec![vec![true; 5]], &config).unwrap();
+        assert!(new_layout[0][1].is_none() && new_layout[0][3].is_none());
+
+        let crowded = Config {
+            spacing: Spacing::Surrounding,
+            ..config.clone()
+        };
+        assert!(execute_from_roster(&roster, &room, &crowded).is_err());
+        let beam_search = Config {
+            algorithm: Algorithm::BeamSearch,
+            ..config
+        };
+        assert!(execute_from_roster(&roster, &room, &beam_search).is_err());
+    }
+
+    #[test]
+    fn first_layout_from_roster() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let roster = (0..20)
+            .map(|i| random_student(&mut rng, i * 3))
+            .collect::<Vec<Student>>();
+        let mut room = vec![vec![true; 5]; 5];
+        room[0][0] = false;
+        let config = Config {
+            loop_cnt: 20000,
+            ..Default::default()
+        };
+
+        let (new_layout, score) = execute_from_roster(&roster, &room, &config).unwrap();
+        assert!(new_layout[0][0].is_none());
+        let mut ids = new_layout
+            .iter()
+            .flatten()
+            .flatten()
+            .map(|s| s.id)
+            .collect::<Vec<usize>>();
+        ids.sort();
+        assert_eq!(ids, roster.iter().map(|s| s.id).collect::<Vec<usize>>());
+        assert_eq!(
+            execute_from_roster(&roster, &room, &config).unwrap(),
+            (new_layout, score)
+        );
+
+        assert!(execute_from_roster(&roster, &vec![vec![true; 4]; 4], &config).is_err());
+        assert!(execute_from_roster(&roster, &vec![], &config).is_err());
+    }
+
+    #[test]
+    fn fuzz_execute_does_not_panic() {
+        let mut rng = ChaCha20Rng::seed_from_u64(2023);
+
+        let mut ok_cnt = 0;
+        for _ in 0..200 {
+            let shape = random_shape(&mut rng);
+            let id_range = shape.iter().sum::<usize>() * 2 + 1;
+            let layout = shape
+                .iter()
+                .map(|&width| {
+                    (0..width)
+                        .map(|_| {
+                            if rng.gen_bool(0.3) {
+                                None
+                            } else {
+                                let id = rng.gen_range(0..id_range);
+                                Some(random_student(&mut rng, id))
+                            }
+                        })
+                        .collect::<Vec<Option<Student>>>()
+                })
+                .collect::<Vec<Vec<Option<Student>>>>();
+
+            if execute(&layout).is_ok() {
+                ok_cnt += 1;
+            }
+        }
+
+        assert!(ok_cnt > 0);
+    }
+
+    #[test]
+    fn fuzz_search_backends_do_not_panic() {
+        let mut rng = ChaCha20Rng::seed_from_u64(2023);
+
+        for _ in 0..500 {
+            let n = rng.gen_range(0..6);
+            let students = (0..n)
+                .map(|i| random_student(&mut rng, i))
+                .collect::<Vec<Student>>();
+
+            let shape = random_shape(&mut rng);
+            let mut random_layout = |shape: &[usize]| {
+                shape
+                    .iter()
+                    .map(|&width| {
+                        (0..width)
+                            .map(|_| {
+                                if rng.gen_bool(0.3) {
+                                    !0
+                                } else {
+                                    rng.gen_range(0..n + 2)
+                                }
+                            })
+                            .collect::<Vec<usize>>()
+                    })
+                    .collect::<SeatAssignment>()
+            };
+            let (previous, new) = (random_layout(&shape), random_layout(&shape));
+
+            let temperatures = [0.0, -1.0, f64::NAN, f64::INFINITY, 1.0, 100.0];
+            let (t1, t2) = (
+                temperatures[rng.gen_range(0..temperatures.len())],
+                temperatures[rng.gen_range(0..temperatures.len())],
+            );
+
+            let config = Config {
+                loop_cnt: 100,
+                temperature1: t1,
+                temperature2: t2,
+                beam_width: 2,
+                ..Default::default()
+            };
+
+            let seats = occupied_seats(&new);
+            let _ = evaluate(&previous, &new, &students, &config.weights);
+            let _ =
+                simulated_annealing::execute(&previous, &new, &seats, &students, &config, &mut rng);
+            let _ = beam_search(&previous, &new, &seats, &students, &config);
+            let _ = tabu_search::execute(&previous, &new, &seats, &students, &config, &mut rng);
+        }
+    }
 }
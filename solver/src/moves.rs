@@ -0,0 +1,254 @@
+use crate::{
+    eval_func::DIR,
+    structs::{Move, MoveWeights, SeatAssignment},
+    utils::swap_seats,
+};
+
+use rand::{seq::index, Rng};
+use rand_distr::{Distribution, WeightedIndex};
+use std::io::Error;
+
+pub const MOVES: [Move; 5] = [
+    Move::Swap,
+    Move::Cycle3,
+    Move::Shift,
+    Move::NeighborSwap,
+    Move::WorstSwap,
+];
+
+pub fn move_distribution(weights: &MoveWeights) -> Result<WeightedIndex<f64>, Error> {
+    WeightedIndex::new([
+        weights.swap,
+        weights.cycle3,
+        weights.shift,
+        weights.neighbor_swap,
+        weights.worst_swap,
+    ])
+    .map_err(|_| {
+        Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Move weights must be non-negative and not all zero",
+        )
+    })
+}
+
+pub fn sample_move<R: Rng>(dist: &WeightedIndex<f64>, rng: &mut R) -> Move {
+    MOVES[dist.sample(rng)]
+}
+
+pub fn occupied_seats(layout: &SeatAssignment) -> Vec<(usize, usize)> {
+    let mut occupied = vec![];
+    for (y, row) in layout.iter().enumerate() {
+        for (x, &student) in row.iter().enumerate() {
+            if student != !0 {
+                occupied.push((x, y));
+            }
+        }
+    }
+    occupied
+}
+
+/// Proposes seats whose students are moved cyclically, see `rotate`. `seats` lists the seats
+/// students may move between, every student of `new` sits on one of them and the others are
+/// vacant. `individual_scores` are the scores of `individual` for `new`, kept up to date by the
+/// caller, and only needed by `Move::WorstSwap`. Returns `None` if the move is not possible in
+/// this layout.
+pub fn propose<R: Rng>(
+    kind: Move,
+    new: &SeatAssignment,
+    seats: &[(usize, usize)],
+    individual_scores: Option<&[i64]>,
+    rng: &mut R,
+) -> Option<Vec<(usize, usize)>> {
+    if seats.len() < 2 {
+        return None;
+    }
+
+    let cycle = match kind {
+        Move::Swap => index::sample(rng, seats.len(), 2)
+            .iter()
+            .map(|i| seats[i])
+            .collect(),
+        Move::Cycle3 => {
+            if seats.len() < 3 {
+                return None;
+            }
+            index::sample(rng, seats.len(), 3)
+                .iter()
+                .map(|i| seats[i])
+                .collect()
+        }
+        Move::Shift => {
+            let (x, y) = seats[rng.gen_range(0..seats.len())];
+            let mut line = if rng.gen_bool(0.5) {
+                seats
+                    .iter()
+                    .copied()
+                    .filter(|&(_, y2)| y2 == y)
+                    .collect::<Vec<(usize, usize)>>()
+            } else {
+                seats
+                    .iter()
+                    .copied()
+                    .filter(|&(x2, _)| x2 == x)
+                    .collect::<Vec<(usize, usize)>>()
+            };
+            if line.len() < 2 {
+                return None;
+            }
+            if rng.gen_bool(0.5) {
+                line.reverse();
+            }
+            line
+        }
+        Move::NeighborSwap => {
+            let (x, y) = seats[rng.gen_range(0..seats.len())];
+            let neighbors = DIR
+                .iter()
+                .map(|d| (x as i32 + d[0], y as i32 + d[1]))
+                .filter(|&(x2, y2)| {
+                    x2 >= 0
+                        && y2 >= 0
+                        && (y2 as usize) < new.len()
+                        && (x2 as usize) < new[y2 as usize].len()
+                        && seats.contains(&(x2 as usize, y2 as usize))
+                })
+                .collect::<Vec<(i32, i32)>>();
+            if neighbors.is_empty() {
+                return None;
+            }
+            let (x2, y2) = neighbors[rng.gen_range(0..neighbors.len())];
+            vec![(x, y), (x2 as usize, y2 as usize)]
+        }
+        Move::WorstSwap => {
+            let individual_scores = individual_scores?;
+            let worst_pos = seats
+                .iter()
+                .copied()
+                .filter(|&(x, y)| new[y][x] != !0)
+                .min_by_key(|&(x, y)| individual_scores[new[y][x]])
+                .unwrap_or(seats[0]);
+            let others = seats
+                .iter()
+                .copied()
+                .filter(|&pos| pos != worst_pos)
+                .collect::<Vec<(usize, usize)>>();
+            vec![worst_pos, others[rng.gen_range(0..others.len())]]
+        }
+    };
+
+    // moving vacant seats around changes nothing
+    if cycle.iter().all(|&(x, y)| new[y][x] == !0) {
+        return None;
+    }
+
+    Some(cycle)
+}
+
+/// Moves the student at `cycle[i]` to `cycle[i + 1]` and the last one to `cycle[0]`.
+pub fn rotate(layout: &mut SeatAssignment, cycle: &[(usize, usize)]) {
+    for i in 1..cycle.len() {
+        swap_seats(layout, cycle[0], cycle[i]);
+    }
+}
+
+/// Reverts `rotate`.
+pub fn rotate_back(layout: &mut SeatAssignment, cycle: &[(usize, usize)]) {
+    for i in (1..cycle.len()).rev() {
+        swap_seats(layout, cycle[0], cycle[i]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::{
+        eval_func::{EvaluationContext, IncrementalEvaluator},
+        scenario::{self, ScenarioConfig},
+        structs::Weights,
+        utils::{compress_student_id, separate_input},
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_rotate() {
+        let mut layout = vec![vec![0, 1, 2, !0]];
+        let cycle = [(0, 0), (1, 0), (2, 0)];
+
+        rotate(&mut layout, &cycle);
+        assert_eq!(layout, vec![vec![2, 0, 1, !0]]);
+
+        rotate_back(&mut layout, &cycle);
+        assert_eq!(layout, vec![vec![0, 1, 2, !0]]);
+    }
+
+    #[test]
+    fn moves_use_only_occupied_seats() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let config = ScenarioConfig {
+            depth: 6,
+            width: 6,
+            vacancy_ratio: 0.3,
+            ..Default::default()
+        };
+        let layout = scenario::generate(&config, &mut rng).unwrap();
+        let (mut previous, mut students) = separate_input(&layout);
+        compress_student_id(&mut students, &mut previous).unwrap();
+
+        let occupied = occupied_seats(&previous);
+        let mut new = previous.clone();
+        let context = EvaluationContext::new(&previous, &students, &Weights::default()).unwrap();
+        let mut evaluator = IncrementalEvaluator::new(&context, &new).unwrap();
+        for kind in MOVES {
+            for _ in 0..100 {
+                let cycle = propose(
+                    kind,
+                    &new,
+                    &occupied,
+                    Some(evaluator.individual_scores()),
+                    &mut rng,
+                );
+                let Some(cycle) = cycle else {
+                    continue;
+                };
+
+                assert!(cycle.len() >= 2);
+                assert!(cycle.iter().all(|pos| occupied.contains(pos)));
+                let mut dedup = cycle.clone();
+                dedup.sort();
+                dedup.dedup();
+                assert_eq!(dedup.len(), cycle.len());
+
+                rotate(&mut new, &cycle);
+                evaluator.rotate(&cycle);
+            }
+        }
+
+        assert_eq!(evaluator.layout(), &new);
+
+        assert_eq!(occupied_seats(&new), occupied);
+        let mut ids = new.iter().flatten().copied().collect::<Vec<usize>>();
+        ids.sort();
+        let mut want = previous.iter().flatten().copied().collect::<Vec<usize>>();
+        want.sort();
+        assert_eq!(ids, want);
+    }
+
+    #[test]
+    fn invalid_move_weights() {
+        assert!(move_distribution(&MoveWeights::default()).is_ok());
+        assert!(move_distribution(&MoveWeights {
+            swap: 0.0,
+            ..Default::default()
+        })
+        .is_err());
+        assert!(move_distribution(&MoveWeights {
+            cycle3: -1.0,
+            ..Default::default()
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,388 @@
+use crate::{
+    input_seed,
+    simulated_annealing::temperature,
+    structs::{Gender, Student, TemperatureSchedule},
+    utils::check_unique_ids,
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::io::Error;
+
+// academic, exercise and leadership abilities, male and needs assistance
+const METRICS: usize = 5;
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PartitionWeights {
+    pub academic: f64,
+    pub exercise: f64,
+    pub leadership: f64,
+    pub gender: f64,
+    pub assistance: f64,
+}
+
+impl Default for PartitionWeights {
+    fn default() -> Self {
+        PartitionWeights {
+            academic: 1000.0,
+            exercise: 1000.0,
+            leadership: 1000.0,
+            gender: 1000.0,
+            assistance: 1000.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct PartitionConfig {
+    /// Number of classes. Their sizes differ by at most one student.
+    pub classes: usize,
+    /// Seed of the random number generator. Derived from the input when `None`.
+    pub seed: Option<u64>,
+    pub loop_cnt: usize,
+    pub temperature1: f64,
+    pub temperature2: f64,
+    pub schedule: TemperatureSchedule,
+    pub weights: PartitionWeights,
+    /// Pairs of student ids who must be in different classes.
+    pub separate: Vec<[usize; 2]>,
+}
+
+impl Default for PartitionConfig {
+    fn default() -> Self {
+        PartitionConfig {
+            classes: 2,
+            seed: None,
+            loop_cnt: 200000,
+            temperature1: 10.0,
+            temperature2: 0.1,
+            schedule: TemperatureSchedule::Linear,
+            weights: PartitionWeights::default(),
+            separate: vec![],
+        }
+    }
+}
+
+/// Students of each class, sorted by id.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Partition {
+    pub classes: Vec<Vec<Student>>,
+    pub score: i64,
+}
+
+/// Divides `students` into classes balanced like the neighbourhoods of a layout: for each of
+/// academic, exercise and leadership abilities, male rate and assistance rate, the lowest class
+/// mean divided by the highest one, times its weight. Students are swapped between classes by
+/// simulated annealing, so class sizes never change.
+pub fn partition(students: &[Student], config: &PartitionConfig) -> Result<Partition, Error> {
+    let n = students.len();
+    if config.classes == 0 || config.classes > n {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "Cannot divide {} students into {} classes",
+                n, config.classes
+            ),
+        ));
+    }
+
+    if ![config.temperature1, config.temperature2]
+        .iter()
+        .all(|t| t.is_finite() && *t > 0.0)
+    {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Temperatures must be positive and finite",
+        ));
+    }
+
+    let mut students = students.to_vec();
+    students.sort_by_key(|s| s.id);
+    let ids = students.iter().map(|s| s.id).collect::<Vec<usize>>();
+    check_unique_ids(&ids)?;
+
+    let mut separated = vec![vec![]; n];
+    for &[a, b] in config.separate.iter() {
+        let (Ok(i), Ok(j)) = (ids.binary_search(&a), ids.binary_search(&b)) else {
+            return Err(Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("Unknown student in separated pair ({}, {})", a, b),
+            ));
+        };
+        separated[i].push(j);
+        separated[j].push(i);
+    }
+
+    let seed = match config.seed {
+        Some(seed) => seed,
+        None => input_seed(&[
+            serde_json::to_string(&students)?,
+            serde_json::to_string(config)?,
+        ]),
+    };
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+
+    let metrics = students
+        .iter()
+        .map(metrics)
+        .collect::<Vec<[f64; METRICS]>>();
+    let mut class = initial_classes(n, config.classes, &separated)?;
+    let mut sizes = vec![0.0; config.classes];
+    let mut sums = vec![[0.0; METRICS]; config.classes];
+    for i in 0..n {
+        sizes[class[i]] += 1.0;
+        for m in 0..METRICS {
+            sums[class[i]][m] += metrics[i][m];
+        }
+    }
+    let mut score = balance(&sums, &sizes, &config.weights);
+
+    let fits = |class: &[usize], i: usize, c: usize| separated[i].iter().all(|&j| class[j] != c);
+    for i in 0..config.loop_cnt {
+        let (a, b) = (rng.gen_range(0..n), rng.gen_range(0..n));
+        let (ca, cb) = (class[a], class[b]);
+        if ca == cb || !fits(&class, a, cb) || !fits(&class, b, ca) {
+            continue;
+        }
+
+        for m in 0..METRICS {
+            let d = metrics[b][m] - metrics[a][m];
+            sums[ca][m] += d;
+            sums[cb][m] -= d;
+        }
+        let new_score = balance(&sums, &sizes, &config.weights);
+
+        let t = temperature(
+            config.schedule,
+            config.temperature1,
+            config.temperature2,
+            i as f64 / config.loop_cnt as f64,
+        );
+        let p = ((new_score as f64 - score as f64) / t).exp();
+        if new_score > score || rng.gen_bool(p.min(1.0)) {
+            class.swap(a, b);
+            score = new_score;
+        } else {
+            for m in 0..METRICS {
+                let d = metrics[b][m] - metrics[a][m];
+                sums[ca][m] -= d;
+                sums[cb][m] += d;
+            }
+        }
+    }
+
+    let mut classes = vec![vec![]; config.classes];
+    for (i, student) in students.into_iter().enumerate() {
+        classes[class[i]].push(student);
+    }
+
+    Ok(Partition { classes, score })
+}
+
+fn metrics(student: &Student) -> [f64; METRICS] {
+    [
+        student.academic_ability as f64,
+        student.exercise_ability as f64,
+        student.leadership_ability as f64,
+        (student.gender == Gender::Male) as usize as f64,
+        student.needs_assistance as usize as f64,
+    ]
+}
+
+/// Deals the students out to classes of equal size, each student to the smallest class which
+/// has none of the students separated from them. Students with the most separations go first,
+/// and earlier students move to other classes when the later ones do not fit.
+fn initial_classes(n: usize, k: usize, separated: &[Vec<usize>]) -> Result<Vec<usize>, Error> {
+    let capacities = (0..k)
+        .map(|c| n / k + (c < n % k) as usize)
+        .collect::<Vec<usize>>();
+
+    let mut order = (0..n).collect::<Vec<usize>>();
+    order.sort_by_key(|&i| std::cmp::Reverse(separated[i].len()));
+
+    let mut class = vec![!0; n];
+    let mut sizes = vec![0; k];
+    if !deal(&order, &capacities, separated, &mut class, &mut sizes) {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Separated students cannot be divided into the classes",
+        ));
+    }
+
+    Ok(class)
+}
+
+/// Deals the students of `order` out by backtracking. Returns whether all of them fit.
+fn deal(
+    order: &[usize],
+    capacities: &[usize],
+    separated: &[Vec<usize>],
+    class: &mut [usize],
+    sizes: &mut [usize],
+) -> bool {
+    let Some((&i, rest)) = order.split_first() else {
+        return true;
+    };
+
+    let mut candidates = (0..capacities.len())
+        .filter(|&c| sizes[c] < capacities[c])
+        .filter(|&c| separated[i].iter().all(|&j| class[j] != c))
+        .collect::<Vec<usize>>();
+    candidates.sort_by_key(|&c| sizes[c]);
+
+    // empty classes of the same capacity are interchangeable
+    let mut tried_empty = vec![];
+    for c in candidates {
+        if sizes[c] == 0 {
+            if tried_empty.contains(&capacities[c]) {
+                continue;
+            }
+            tried_empty.push(capacities[c]);
+        }
+
+        class[i] = c;
+        sizes[c] += 1;
+        if deal(rest, capacities, separated, class, sizes) {
+            return true;
+        }
+        class[i] = !0;
+        sizes[c] -= 1;
+    }
+
+    false
+}
+
+fn balance(sums: &[[f64; METRICS]], sizes: &[f64], weights: &PartitionWeights) -> i64 {
+    let weights = [
+        weights.academic,
+        weights.exercise,
+        weights.leadership,
+        weights.gender,
+        weights.assistance,
+    ];
+
+    let mut score = 0i64;
+    for m in 0..METRICS {
+        let means = sums.iter().zip(sizes).map(|(sum, size)| sum[m] / size);
+        let (min, max) = means.fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), x| {
+            (min.min(x), max.max(x))
+        });
+        // no class has any of it, which is balanced too
+        let ratio = if max > 0.0 { min / max } else { 1.0 };
+        score = score.saturating_add((weights[m] * ratio) as i64);
+    }
+
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn students(n: usize, rng: &mut ChaCha20Rng) -> Vec<Student> {
+        (0..n)
+            .map(|id| Student {
+                id,
+                name: format!("Student {}", id),
+                academic_ability: rng.gen_range(1..=5),
+                exercise_ability: rng.gen_range(1..=5),
+                leadership_ability: rng.gen_range(1..=5),
+                needs_assistance: rng.gen_bool(0.1),
+                gender: if rng.gen_bool(0.5) {
+                    Gender::Male
+                } else {
+                    Gender::Female
+                },
+            })
+            .collect()
+    }
+
+    #[test]
+    fn balanced_classes() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let students = students(100, &mut rng);
+        let config = PartitionConfig {
+            classes: 3,
+            loop_cnt: 20000,
+            separate: vec![[0, 1], [1, 2], [0, 2], [10, 20]],
+            ..Default::default()
+        };
+
+        let divided = partition(&students, &config).unwrap();
+        assert_eq!(
+            divided
+                .classes
+                .iter()
+                .map(|class| class.len())
+                .collect::<Vec<usize>>(),
+            vec![34, 33, 33]
+        );
+        let class_of = |id: usize| {
+            divided
+                .classes
+                .iter()
+                .position(|class| class.iter().any(|s| s.id == id))
+                .unwrap()
+        };
+        for [a, b] in config.separate.iter() {
+            assert_ne!(class_of(*a), class_of(*b));
+        }
+
+        // annealing improves on the dealt classes
+        let no_annealing = PartitionConfig {
+            loop_cnt: 0,
+            ..config.clone()
+        };
+        assert!(divided.score > partition(&students, &no_annealing).unwrap().score);
+    }
+
+    #[test]
+    fn separations_need_another_class_order() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let students = students(5, &mut rng);
+        // 0 only fits into the smaller class, with 4
+        let config = PartitionConfig {
+            separate: vec![[0, 1], [0, 2], [0, 3]],
+            loop_cnt: 1000,
+            ..Default::default()
+        };
+
+        let divided = partition(&students, &config).unwrap();
+        let class_of_0 = divided
+            .classes
+            .iter()
+            .find(|class| class.iter().any(|s| s.id == 0))
+            .unwrap();
+        assert_eq!(
+            class_of_0.iter().map(|s| s.id).collect::<Vec<usize>>(),
+            vec![0, 4]
+        );
+    }
+
+    #[test]
+    fn invalid_partitions() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let students = students(4, &mut rng);
+
+        let too_many = PartitionConfig {
+            classes: 5,
+            ..Default::default()
+        };
+        assert!(partition(&students, &too_many).is_err());
+
+        let unknown = PartitionConfig {
+            separate: vec![[0, 10]],
+            ..Default::default()
+        };
+        assert!(partition(&students, &unknown).is_err());
+
+        // three students who must be apart do not fit into two classes
+        let clique = PartitionConfig {
+            separate: vec![[0, 1], [1, 2], [0, 2]],
+            ..Default::default()
+        };
+        assert!(partition(&students, &clique).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::{
+    structs::{Config, Layout, Room, Student, Weights},
+    utils::{check_input, layout_size},
+};
+
+use serde_json::{json, Value};
+use std::io::Error;
+
+/// Version written by `save`. Files of older versions are migrated by `load`.
+pub const PROJECT_VERSION: u64 = 2;
+
+/// Everything the app keeps about a class, saved as one JSON file.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Project {
+    pub version: u64,
+    pub room: Room,
+    pub roster: Vec<Student>,
+    /// Layouts of the class, oldest first. The last one is the current layout.
+    pub history: Vec<HistoryEntry>,
+    /// Solver settings including the constraints.
+    pub settings: Config,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Project {
+            version: PROJECT_VERSION,
+            room: vec![],
+            roster: vec![],
+            history: vec![],
+            settings: default_settings(),
+        }
+    }
+}
+
+/// Settings of a new project. A project keeps the layouts of the year, so the students are also
+/// rotated through the zones of the room.
+pub fn default_settings() -> Config {
+    Config {
+        weights: Weights {
+            zone_repeat: 1000.0,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+/// Layout of the class at some point of the year.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HistoryEntry {
+    /// Seconds since the unix epoch, 0 for layouts of files without timestamps.
+    pub timestamp: u64,
+    /// E.g. "Term 2, week 3".
+    pub label: String,
+    pub layout: Layout,
+}
+
+impl Project {
+    /// Adds `layout` as the current layout.
+    pub fn record(&mut self, layout: Layout, label: &str, timestamp: u64) {
+        self.history.push(HistoryEntry {
+            timestamp,
+            label: label.to_string(),
+            layout,
+        });
+    }
+
+    /// Makes `layout` the current layout, or the first one of an empty history, and takes the
+    /// roster and the room from it. Desks of the room stay where the size of the room is
+    /// unchanged, and every seat of a student is a desk.
+    pub fn set_current(&mut self, layout: Layout, timestamp: u64) {
+        let same_size = self.room.len() == layout.len()
+            && self
+                .room
+                .iter()
+                .zip(layout.iter())
+                .all(|(desks, row)| desks.len() == row.len());
+        if !same_size {
+            self.room = layout.iter().map(|row| vec![true; row.len()]).collect();
+        }
+        for (desks, row) in self.room.iter_mut().zip(layout.iter()) {
+            for (desk, seat) in desks.iter_mut().zip(row.iter()) {
+                *desk |= seat.is_some();
+            }
+        }
+        self.roster = layout.iter().flatten().flatten().cloned().collect();
+
+        match self.history.last_mut() {
+            Some(entry) => entry.layout = layout,
+            None => self.record(layout, "", timestamp),
+        }
+    }
+
+    /// Makes a copy of the entry at `index` the current layout and returns it, so that reverting
+    /// keeps the layouts in between.
+    pub fn restore(&mut self, index: usize, timestamp: u64) -> Result<&HistoryEntry, Error> {
+        let entry = self.history.get(index).ok_or_else(|| {
+            Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("History has no entry {}", index),
+            )
+        })?;
+
+        let layout = entry.layout.clone();
+        let label = format!("{} (restored)", entry.label);
+        self.record(layout, &label, timestamp);
+
+        Ok(&self.history[self.history.len() - 1])
+    }
+
+    /// Layouts of the history, the most recent one first, as taken by
+    /// `execute_with_history`.
+    pub fn past_layouts(&self) -> Vec<Layout> {
+        self.history
+            .iter()
+            .rev()
+            .map(|entry| entry.layout.clone())
+            .collect()
+    }
+}
+
+/// Migrations from each version to the next one, indexed by the old version.
+const MIGRATIONS: [fn(Value) -> Result<Value, Error>; PROJECT_VERSION as usize] =
+    [migrate_v0, migrate_v1];
+
+fn invalid_data(msg: String) -> Error {
+    Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+/// Reads a project file of any version up to `PROJECT_VERSION`.
+pub fn load(text: &str) -> Result<Project, Error> {
+    let mut value = serde_json::from_str::<Value>(text)?;
+
+    let version = match &value {
+        // files written before projects existed only hold the layout
+        Value::Array(_) => 0,
+        Value::Object(object) => object
+            .get("version")
+            .and_then(Value::as_u64)
+            .ok_or_else(|| invalid_data("Project file has no version".to_string()))?,
+        _ => return Err(invalid_data("Project file is not a project".to_string())),
+    };
+    if version > PROJECT_VERSION {
+        return Err(invalid_data(format!(
+            "Project file version {} is newer than the supported version {}",
+            version, PROJECT_VERSION
+        )));
+    }
+
+    for migration in MIGRATIONS[version as usize..].iter() {
+        value = migration(value)?;
+    }
+
+    let project = serde_json::from_value::<Project>(value)?;
+    for entry in project.history.iter() {
+        check_input(&entry.layout)?;
+    }
+
+    Ok(project)
+}
+
+/// Writes `project` as the current version.
+pub fn save(project: &Project) -> Result<String, Error> {
+    Ok(serde_json::to_string_pretty(&Project {
+        version: PROJECT_VERSION,
+        ..project.clone()
+    })?)
+}
+
+/// A bare layout becomes the only layout of the history, seated in a room of its size.
+fn migrate_v0(value: Value) -> Result<Value, Error> {
+    let layout = serde_json::from_value::<Layout>(value)?;
+    let (depth, width) = layout_size(&layout)?;
+    let roster = layout
+        .iter()
+        .flatten()
+        .flatten()
+        .cloned()
+        .collect::<Vec<Student>>();
+
+    Ok(json!({
+        "version": 1,
+        "room": vec![vec![true; width]; depth],
+        "roster": roster,
+        "history": [layout],
+        "settings": {},
+    }))
+}
+
+/// Layouts of the history get timestamps and labels.
+fn migrate_v1(mut value: Value) -> Result<Value, Error> {
+    let history = serde_json::from_value::<Vec<Layout>>(value["history"].take())?;
+
+    value["version"] = json!(2);
+    value["history"] = history
+        .into_iter()
+        .enumerate()
+        .map(|(i, layout)| {
+            json!({
+                "timestamp": 0,
+                "label": format!("Layout {}", i + 1),
+                "layout": layout,
+            })
+        })
+        .collect();
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use crate::{
+        scenario::{self, ScenarioConfig},
+        structs::{Algorithm, Constraint},
+    };
+
+    use super::*;
+
+    fn layout() -> Layout {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let config = ScenarioConfig {
+            depth: 3,
+            width: 4,
+            vacancy_ratio: 0.2,
+            ..Default::default()
+        };
+        scenario::generate(&config, &mut rng).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        let layout = layout();
+        let mut project = Project {
+            room: vec![vec![true; 4]; 3],
+            roster: layout.iter().flatten().flatten().cloned().collect(),
+            settings: Config {
+                algorithm: Algorithm::BranchAndBound,
+                time_limit_ms: Some(1000),
+                constraints: vec![Constraint::Separate { students: [1, 2] }],
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        project.record(layout.clone(), "Term 1", 1_700_000_000);
+        project.record(layout, "Term 2, week 3", 1_710_000_000);
+
+        let text = save(&project).unwrap();
+        assert_eq!(load(&text).unwrap(), project);
+        assert_eq!(save(&load(&text).unwrap()).unwrap(), text);
+    }
+
+    #[test]
+    fn migrate_bare_layout() {
+        let layout = layout();
+        let text = serde_json::to_string(&layout).unwrap();
+
+        let project = load(&text).unwrap();
+        assert_eq!(project.version, PROJECT_VERSION);
+        assert_eq!(project.room, vec![vec![true; 4]; 3]);
+        assert_eq!(project.history.len(), 1);
+        assert_eq!(project.history[0].layout, layout);
+        assert_eq!(project.history[0].label, "Layout 1");
+        assert_eq!(
+            project.roster.len(),
+            layout.iter().flatten().flatten().count()
+        );
+        assert_eq!(project.settings, Config::default());
+    }
+
+    #[test]
+    fn current_layout_updates_room_and_roster() {
+        let layout = layout();
+        let mut project = Project::default();
+        project.set_current(layout.clone(), 1);
+        assert_eq!(project.room, vec![vec![true; 4]; 3]);
+        assert_eq!(project.history.len(), 1);
+
+        // a desk taken out of the room stays out while the size is the same
+        let (x, y) = (0..12)
+            .map(|i| (i % 4, i / 4))
+            .find(|&(x, y)| layout[y][x].is_none())
+            .unwrap();
+        project.room[y][x] = false;
+        project.set_current(layout.clone(), 2);
+        assert!(!project.room[y][x]);
+
+        // a resized layout brings its own room and roster
+        let smaller = layout[..2].to_vec();
+        project.set_current(smaller.clone(), 3);
+        assert_eq!(project.room, vec![vec![true; 4]; 2]);
+        assert_eq!(
+            project.roster,
+            smaller
+                .iter()
+                .flatten()
+                .flatten()
+                .cloned()
+                .collect::<Vec<Student>>()
+        );
+        assert_eq!(project.history.len(), 1);
+        assert_eq!(project.history[0].layout, smaller);
+    }
+
+    #[test]
+    fn migrate_v1_history() {
+        let layout = layout();
+        let text = json!({
+            "version": 1,
+            "room": vec![vec![true; 4]; 3],
+            "roster": [],
+            "history": [layout, layout],
+            "settings": {},
+        })
+        .to_string();
+
+        let project = load(&text).unwrap();
+        assert_eq!(
+            project
+                .history
+                .iter()
+                .map(|entry| (entry.timestamp, entry.label.as_str()))
+                .collect::<Vec<(u64, &str)>>(),
+            vec![(0, "Layout 1"), (0, "Layout 2")]
+        );
+    }
+
+    #[test]
+    fn restore_keeps_history() {
+        let layout = layout();
+        let mut moved = layout.clone();
+        moved.swap(0, 1);
+
+        let mut project = Project::default();
+        project.record(layout.clone(), "Term 1", 1);
+        project.record(moved.clone(), "Term 2", 2);
+
+        assert_eq!(project.past_layouts(), vec![moved.clone(), layout.clone()]);
+        assert_eq!(project.restore(0, 3).unwrap().label, "Term 1 (restored)");
+        assert_eq!(project.past_layouts(), vec![layout.clone(), moved, layout]);
+        assert!(project.restore(5, 4).is_err());
+    }
+
+    #[test]
+    fn reject_unknown_files() {
+        assert!(load("").is_err());
+        assert!(load("42").is_err());
+        assert!(load("{}").is_err());
+        assert!(load("[]").is_err());
+        assert!(load(&format!("{{\"version\": {}}}", PROJECT_VERSION + 1)).is_err());
+        assert!(load("{\"version\": 1, \"history\": [[[]]]}").is_err());
+        assert!(load("{\"version\": 2, \"history\": [{\"timestamp\": 0}]}").is_err());
+    }
+}
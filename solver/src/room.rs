@@ -0,0 +1,160 @@
+use crate::{
+    structs::{Layout, Room, RoomMapping, Student},
+    utils::{check_input, layout_size, room_seats},
+};
+
+use std::io::Error;
+
+/// Moves the students of `layout` onto the desks of `room`. Students whose seat given by
+/// `mapping` is a desk of `room` sit there, the others take the nearest free desk, so most
+/// neighbours of the layout stay neighbours.
+pub fn map_to_room(
+    layout: &[Vec<Option<Student>>],
+    room: &Room,
+    mapping: RoomMapping,
+) -> Result<Layout, Error> {
+    check_input(layout)?;
+    let size = layout_size(layout)?;
+    let (new_depth, new_width) = layout_size(room)?;
+
+    let mut students = vec![];
+    for (y, row) in layout.iter().enumerate() {
+        for (x, seat) in row.iter().enumerate() {
+            if let Some(student) = seat {
+                students.push((
+                    target((x, y), size, (new_depth, new_width), mapping),
+                    student,
+                ));
+            }
+        }
+    }
+
+    let seats = room_seats(room);
+    if seats.len() < students.len() {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!(
+                "The room has {} seats for {} students",
+                seats.len(),
+                students.len()
+            ),
+        ));
+    }
+
+    let mut new = vec![vec![None; new_width]; new_depth];
+    let mut unplaced = vec![];
+    for &((x, y), student) in students.iter() {
+        if x >= 0
+            && y >= 0
+            && (y as usize) < new_depth
+            && (x as usize) < new_width
+            && room[y as usize][x as usize]
+            && new[y as usize][x as usize].is_none()
+        {
+            new[y as usize][x as usize] = Some(student.clone());
+        } else {
+            unplaced.push(((x, y), student));
+        }
+    }
+
+    for ((x, y), student) in unplaced {
+        // there are at least as many seats as students, so a desk is free
+        let &(x2, y2) = seats
+            .iter()
+            .filter(|&&(x2, y2)| new[y2][x2].is_none())
+            .min_by_key(|&&(x2, y2)| (x2 as i64 - x).abs() + (y2 as i64 - y).abs())
+            .unwrap();
+        new[y2][x2] = Some(student.clone());
+    }
+
+    Ok(new)
+}
+
+/// Seat at `pos` of a layout of `size` in a room of `new_size`, possibly outside of the room.
+fn target(
+    (x, y): (usize, usize),
+    (depth, width): (usize, usize),
+    (new_depth, new_width): (usize, usize),
+    mapping: RoomMapping,
+) -> (i64, i64) {
+    match mapping {
+        RoomMapping::SeatCoordinates => (x as i64, y as i64),
+        RoomMapping::NearestPosition => {
+            let scale = |i: usize, len: usize, new_len: usize| {
+                ((i as f64 + 0.5) * new_len as f64 / len as f64 - 0.5).round() as i64
+            };
+            (scale(x, width, new_width), scale(y, depth, new_depth))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::Gender;
+
+    use super::*;
+
+    fn student(id: usize) -> Option<Student> {
+        Some(Student {
+            id,
+            name: format!("Student {}", id),
+            academic_ability: 3,
+            exercise_ability: 3,
+            leadership_ability: 3,
+            needs_assistance: false,
+            gender: Gender::Male,
+        })
+    }
+
+    fn ids(layout: &Layout) -> Vec<Vec<usize>> {
+        layout
+            .iter()
+            .map(|row| row.iter().map(|s| s.as_ref().map_or(0, |s| s.id)).collect())
+            .collect()
+    }
+
+    #[test]
+    fn added_column_keeps_seats() {
+        let layout = vec![vec![student(1), student(2)], vec![student(3), None]];
+        let room = vec![vec![true; 3]; 2];
+
+        let new = map_to_room(&layout, &room, RoomMapping::SeatCoordinates).unwrap();
+        assert_eq!(ids(&new), vec![vec![1, 2, 0], vec![3, 0, 0]]);
+    }
+
+    #[test]
+    fn removed_back_row_moves_to_nearest_desks() {
+        let layout = vec![
+            vec![student(1), None, student(2)],
+            vec![None, student(3), None],
+            vec![student(4), None, None],
+        ];
+        let room = vec![vec![true; 3]; 2];
+
+        let new = map_to_room(&layout, &room, RoomMapping::SeatCoordinates).unwrap();
+        assert_eq!(ids(&new), vec![vec![1, 0, 2], vec![4, 3, 0]]);
+    }
+
+    #[test]
+    fn nearest_position_scales_layout() {
+        let layout = vec![vec![student(1), student(2), student(3)]];
+        let room = vec![vec![true; 5]];
+
+        let new = map_to_room(&layout, &room, RoomMapping::NearestPosition).unwrap();
+        assert_eq!(ids(&new), vec![vec![1, 0, 2, 0, 3]]);
+    }
+
+    #[test]
+    fn room_without_enough_desks() {
+        let layout = vec![vec![student(1), student(2)]];
+
+        assert!(map_to_room(&layout, &vec![vec![true]], RoomMapping::SeatCoordinates).is_err());
+        assert!(map_to_room(&layout, &vec![], RoomMapping::SeatCoordinates).is_err());
+        assert!(map_to_room(
+            &layout,
+            &vec![vec![true, false, true]],
+            RoomMapping::SeatCoordinates
+        )
+        .is_ok());
+    }
+}
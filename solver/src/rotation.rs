@@ -0,0 +1,217 @@
+use crate::{
+    export::{seats, ExportFormat},
+    structs::{Layout, PastLayouts},
+    utils::{check_input, layout_size},
+};
+
+use std::{collections::BTreeMap, io::Error};
+
+/// Part of the room a student should visit over the year. Corner seats are in two zones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Zone {
+    /// The first row.
+    Front,
+    /// The last row.
+    Back,
+    /// Column 1 as printed on the PDF.
+    Window,
+    /// The last column.
+    Aisle,
+}
+
+pub const ZONES: [Zone; 4] = [Zone::Front, Zone::Back, Zone::Window, Zone::Aisle];
+
+impl Zone {
+    /// Whether the seat at `(x, y)` of a `depth` x `width` layout is in the zone.
+    pub fn contains(self, (x, y): (usize, usize), depth: usize, width: usize) -> bool {
+        match self {
+            Zone::Front => y == 0,
+            Zone::Back => y + 1 == depth,
+            Zone::Window => x == 0,
+            Zone::Aisle => x + 1 == width,
+        }
+    }
+}
+
+/// Zones of the seat at `pos`.
+pub fn seat_zones(pos: (usize, usize), depth: usize, width: usize) -> Vec<Zone> {
+    ZONES
+        .into_iter()
+        .filter(|zone| zone.contains(pos, depth, width))
+        .collect()
+}
+
+/// How often each student sat in each zone of `previous`, in the order of `ZONES`.
+pub(crate) fn zone_counts(previous: &dyn PastLayouts, n: usize) -> Vec<[usize; 4]> {
+    let mut counts = vec![[0; 4]; n];
+    for layout in previous.layouts() {
+        let (depth, width) = (layout.len(), layout[0].len());
+        for y in 0..depth {
+            for x in 0..width {
+                if layout[y][x] == !0 {
+                    continue;
+                }
+                for (k, zone) in ZONES.iter().enumerate() {
+                    if zone.contains((x, y), depth, width) {
+                        counts[layout[y][x]][k] += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    counts
+}
+
+/// Number of earlier layouts in which a student with `counts` sat in a zone of `pos`, once per
+/// zone.
+pub(crate) fn zone_repeats(
+    counts: &[usize; 4],
+    pos: (usize, usize),
+    depth: usize,
+    width: usize,
+) -> usize {
+    ZONES
+        .iter()
+        .zip(counts)
+        .filter(|(zone, _)| zone.contains(pos, depth, width))
+        .map(|(_, cnt)| cnt)
+        .sum()
+}
+
+/// How often a student sat in each zone.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ZoneExposure {
+    pub id: usize,
+    pub name: String,
+    /// Number of layouts the student is in.
+    pub layouts: usize,
+    pub front: usize,
+    pub back: usize,
+    pub window: usize,
+    pub aisle: usize,
+}
+
+/// Zone exposure of each student of `history`, sorted by id. The name is taken from the first
+/// layout the student is in.
+pub fn zone_report(history: &[Layout]) -> Result<Vec<ZoneExposure>, Error> {
+    let mut report = BTreeMap::new();
+    for layout in history.iter() {
+        check_input(layout)?;
+        let (depth, width) = layout_size(layout)?;
+
+        for (row, column, student) in seats(layout) {
+            let exposure = report.entry(student.id).or_insert_with(|| ZoneExposure {
+                id: student.id,
+                name: student.name.clone(),
+                layouts: 0,
+                front: 0,
+                back: 0,
+                window: 0,
+                aisle: 0,
+            });
+            exposure.layouts += 1;
+            for zone in seat_zones((column - 1, row - 1), depth, width) {
+                *match zone {
+                    Zone::Front => &mut exposure.front,
+                    Zone::Back => &mut exposure.back,
+                    Zone::Window => &mut exposure.window,
+                    Zone::Aisle => &mut exposure.aisle,
+                } += 1;
+            }
+        }
+    }
+
+    Ok(report.into_values().collect())
+}
+
+/// `zone_report` of `history` as CSV with a header row, or as a JSON list.
+pub fn export_zone_report(history: &[Layout], format: ExportFormat) -> Result<String, Error> {
+    let report = zone_report(history)?;
+
+    match format {
+        ExportFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for exposure in report.iter() {
+                writer.serialize(exposure)?;
+            }
+            let bytes = writer
+                .into_inner()
+                .map_err(|err| Error::other(err.to_string()))?;
+
+            String::from_utf8(bytes).map_err(Error::other)
+        }
+        ExportFormat::Json => Ok(serde_json::to_string_pretty(&report)?),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::structs::{Gender, Student};
+
+    use super::*;
+
+    fn student(id: usize) -> Option<Student> {
+        Some(Student {
+            id,
+            name: format!("Student {}", id),
+            academic_ability: 3,
+            exercise_ability: 3,
+            leadership_ability: 3,
+            needs_assistance: false,
+            gender: Gender::Male,
+        })
+    }
+
+    #[test]
+    fn exposure_over_the_history() {
+        let history = vec![
+            vec![
+                vec![student(1), student(2), student(3)],
+                vec![student(4), None, student(5)],
+                vec![None, student(6), None],
+            ],
+            vec![
+                vec![student(6), student(5), student(4)],
+                vec![student(3), student(2), student(1)],
+            ],
+        ];
+
+        let report = zone_report(&history).unwrap();
+        let counts = report
+            .iter()
+            .map(|e| (e.id, e.layouts, e.front, e.back, e.window, e.aisle))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            counts,
+            vec![
+                (1, 2, 1, 1, 1, 1),
+                (2, 2, 1, 1, 0, 0),
+                (3, 2, 1, 1, 1, 1),
+                (4, 2, 1, 0, 1, 1),
+                (5, 2, 1, 0, 0, 1),
+                (6, 2, 1, 1, 1, 0),
+            ]
+        );
+
+        let csv = export_zone_report(&history[1..], ExportFormat::Csv).unwrap();
+        assert_eq!(
+            csv.lines().take(2).collect::<Vec<&str>>(),
+            vec![
+                "id,name,layouts,front,back,window,aisle",
+                "1,Student 1,1,0,1,0,1"
+            ]
+        );
+    }
+
+    #[test]
+    fn repeats_of_the_zones_of_a_seat() {
+        let previous = vec![vec![vec![0, 1], vec![2, !0]], vec![vec![1, 0], vec![!0, 2]]];
+        let counts = zone_counts(&previous, 3);
+        assert_eq!(counts, vec![[2, 0, 1, 1], [2, 0, 1, 1], [0, 2, 1, 1]]);
+
+        // the back corner on the window side of a 3 x 3 room
+        assert_eq!(zone_repeats(&counts[2], (0, 2), 3, 3), 3);
+        assert_eq!(zone_repeats(&counts[2], (1, 1), 3, 3), 0);
+    }
+}
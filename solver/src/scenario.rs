@@ -0,0 +1,317 @@
+use crate::structs::{Gender, Layout, Student};
+
+use rand::{seq::SliceRandom, Rng};
+use rand_distr::{Distribution, Normal};
+use std::io::Error;
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum AbilityDistribution {
+    /// Rounded normal distribution clamped to 1..=5.
+    Normal { mean: f64, sigma: f64 },
+    /// Uniform distribution over `min..=max`.
+    Uniform { min: usize, max: usize },
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum VacancyPattern {
+    /// Vacant seats are scattered over the whole room.
+    #[default]
+    Random,
+    /// Vacant seats fill the room from the back row, right to left.
+    Back,
+    /// Vacant seats fill the room from the rightmost column, back to front.
+    Side,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct ScenarioConfig {
+    pub depth: usize,
+    pub width: usize,
+    pub vacancy_ratio: f64,
+    pub vacancy_pattern: VacancyPattern,
+    pub male_ratio: f64,
+    pub assistance_ratio: f64,
+    pub academic: AbilityDistribution,
+    pub exercise: AbilityDistribution,
+    pub leadership: AbilityDistribution,
+    pub first_id: usize,
+    /// Consecutive student ids differ by a random amount in `1..=max_id_gap`.
+    pub max_id_gap: usize,
+}
+
+impl Default for ScenarioConfig {
+    fn default() -> Self {
+        let ability = AbilityDistribution::Normal {
+            mean: 3.0,
+            sigma: 1.0,
+        };
+
+        ScenarioConfig {
+            depth: 5,
+            width: 6,
+            vacancy_ratio: 0.0,
+            vacancy_pattern: VacancyPattern::Random,
+            male_ratio: 0.5,
+            assistance_ratio: 0.1,
+            academic: ability,
+            exercise: ability,
+            leadership: ability,
+            first_id: 0,
+            max_id_gap: 1,
+        }
+    }
+}
+
+fn invalid_input(msg: &str) -> Error {
+    Error::new(std::io::ErrorKind::InvalidInput, msg)
+}
+
+fn sample_ability<R: Rng>(dist: &AbilityDistribution, rng: &mut R) -> Result<usize, Error> {
+    match *dist {
+        AbilityDistribution::Normal { mean, sigma } => {
+            let normal = Normal::new(mean, sigma)
+                .map_err(|_| invalid_input("Invalid normal ability distribution"))?;
+            Ok((normal.sample(rng).round().max(0.0) as usize).clamp(1, 5))
+        }
+        AbilityDistribution::Uniform { min, max } => {
+            if min > max {
+                return Err(invalid_input("Invalid uniform ability distribution"));
+            }
+            Ok(rng.gen_range(min..=max))
+        }
+    }
+}
+
+/// Generates a previous layout of students.
+///
+/// The ids of the students are not compressed, so the result has to go through the same
+/// preprocessing as the input of `execute`.
+pub fn generate<R: Rng>(config: &ScenarioConfig, rng: &mut R) -> Result<Layout, Error> {
+    let (depth, width) = (config.depth, config.width);
+    let seat_cnt = depth * width;
+
+    for ratio in [
+        config.vacancy_ratio,
+        config.male_ratio,
+        config.assistance_ratio,
+    ] {
+        if !(0.0..=1.0).contains(&ratio) {
+            return Err(invalid_input("Ratios must be between 0 and 1"));
+        }
+    }
+    if config.max_id_gap == 0 {
+        return Err(invalid_input("max_id_gap must be positive"));
+    }
+
+    let n = seat_cnt - (seat_cnt as f64 * config.vacancy_ratio).round() as usize;
+    if n == 0 {
+        return Err(invalid_input("Scenario has no students"));
+    }
+
+    let male_cnt = (n as f64 * config.male_ratio).round() as usize;
+    let assistance_cnt = (n as f64 * config.assistance_ratio).round() as usize;
+
+    let mut genders = (0..n)
+        .map(|i| {
+            if i < male_cnt {
+                Gender::Male
+            } else {
+                Gender::Female
+            }
+        })
+        .collect::<Vec<Gender>>();
+    genders.shuffle(rng);
+
+    let mut needs_assistance = (0..n).map(|i| i < assistance_cnt).collect::<Vec<bool>>();
+    needs_assistance.shuffle(rng);
+
+    let mut students = vec![];
+    let mut id = config.first_id;
+    for i in 0..n {
+        if i > 0 {
+            id += rng.gen_range(1..=config.max_id_gap);
+        }
+
+        students.push(Student {
+            id,
+            name: format!("Student {}", id),
+            academic_ability: sample_ability(&config.academic, rng)?,
+            exercise_ability: sample_ability(&config.exercise, rng)?,
+            leadership_ability: sample_ability(&config.leadership, rng)?,
+            needs_assistance: needs_assistance[i],
+            gender: genders[i],
+        });
+    }
+    students.shuffle(rng);
+
+    let mut seats = match config.vacancy_pattern {
+        VacancyPattern::Random => {
+            let mut seats = (0..seat_cnt)
+                .map(|i| (i % width, i / width))
+                .collect::<Vec<(usize, usize)>>();
+            seats.shuffle(rng);
+            seats
+        }
+        VacancyPattern::Back => (0..seat_cnt)
+            .map(|i| (i % width, i / width))
+            .collect::<Vec<(usize, usize)>>(),
+        VacancyPattern::Side => (0..seat_cnt)
+            .map(|i| (i / depth, i % depth))
+            .collect::<Vec<(usize, usize)>>(),
+    };
+    seats.truncate(n);
+
+    let mut layout = vec![vec![None; width]; depth];
+    for ((x, y), student) in seats.into_iter().zip(students) {
+        layout[y][x] = Some(student);
+    }
+
+    Ok(layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+
+    fn students(layout: &Layout) -> Vec<&Student> {
+        layout.iter().flatten().flatten().collect()
+    }
+
+    #[test]
+    fn default_scenario_is_full_room() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let layout = generate(&ScenarioConfig::default(), &mut rng).unwrap();
+
+        assert_eq!(layout.len(), 5);
+        assert!(layout.iter().all(|row| row.len() == 6));
+
+        let students = students(&layout);
+        assert_eq!(students.len(), 30);
+        assert_eq!(students.iter().filter(|s| s.needs_assistance).count(), 3);
+        assert_eq!(
+            students.iter().filter(|s| s.gender == Gender::Male).count(),
+            15
+        );
+
+        let mut ids = students.iter().map(|s| s.id).collect::<Vec<usize>>();
+        ids.sort();
+        assert_eq!(ids, (0..30).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn vacancy_patterns() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let config = ScenarioConfig {
+            depth: 4,
+            width: 5,
+            vacancy_ratio: 0.25,
+            ..Default::default()
+        };
+
+        let back = generate(
+            &ScenarioConfig {
+                vacancy_pattern: VacancyPattern::Back,
+                ..config.clone()
+            },
+            &mut rng,
+        )
+        .unwrap();
+        assert!(back[3].iter().all(|seat| seat.is_none()));
+        assert!(back[..3].iter().flatten().all(|seat| seat.is_some()));
+
+        let side = generate(
+            &ScenarioConfig {
+                vacancy_ratio: 0.2,
+                vacancy_pattern: VacancyPattern::Side,
+                ..config.clone()
+            },
+            &mut rng,
+        )
+        .unwrap();
+        assert!(side.iter().all(|row| row[4].is_none() && row[3].is_some()));
+
+        let random = generate(&config, &mut rng).unwrap();
+        assert_eq!(students(&random).len(), 15);
+    }
+
+    #[test]
+    fn non_contiguous_ids() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let config = ScenarioConfig {
+            first_id: 1,
+            max_id_gap: 5,
+            ..Default::default()
+        };
+        let layout = generate(&config, &mut rng).unwrap();
+
+        let mut ids = students(&layout)
+            .iter()
+            .map(|s| s.id)
+            .collect::<Vec<usize>>();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), 30);
+        assert_eq!(ids[0], 1);
+        assert!(ids.windows(2).all(|w| w[1] - w[0] <= 5));
+    }
+
+    #[test]
+    fn invalid_configs() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let invalid = [
+            ScenarioConfig {
+                vacancy_ratio: 1.0,
+                ..Default::default()
+            },
+            ScenarioConfig {
+                male_ratio: 1.5,
+                ..Default::default()
+            },
+            ScenarioConfig {
+                max_id_gap: 0,
+                ..Default::default()
+            },
+            ScenarioConfig {
+                academic: AbilityDistribution::Uniform { min: 5, max: 1 },
+                ..Default::default()
+            },
+        ];
+
+        for config in invalid.iter() {
+            assert!(generate(config, &mut rng).is_err());
+        }
+    }
+
+    #[test]
+    fn generated_scenarios_can_be_solved() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let solver_config = crate::structs::Config {
+            loop_cnt: 10000,
+            ..Default::default()
+        };
+
+        for (depth, width, vacancy_ratio, vacancy_pattern) in [
+            (6, 6, 0.1, VacancyPattern::Random),
+            (4, 8, 0.2, VacancyPattern::Back),
+            (7, 5, 0.3, VacancyPattern::Side),
+        ] {
+            let config = ScenarioConfig {
+                depth,
+                width,
+                vacancy_ratio,
+                vacancy_pattern,
+                first_id: 1,
+                max_id_gap: 3,
+                ..Default::default()
+            };
+            let layout = generate(&config, &mut rng).unwrap();
+
+            let res = crate::execute_with_config(&layout, &solver_config);
+            assert!(res.is_ok());
+        }
+    }
+}
@@ -1,107 +1,449 @@
 use crate::{
-    eval_func::evaluate,
-    structs::{SeatAssignment, Student},
+    constraints::{feasible_layout, Rules},
+    eval_func::{objective_score, EvaluationContext, IncrementalEvaluator},
+    moves::{move_distribution, propose, rotate, rotate_back, sample_move},
+    structs::{
+        Config, MoveStats, Objective, PastLayouts, SeatAssignment, Student, TemperatureSchedule,
+        Weights,
+    },
     utils::swap_seats,
 };
 
-use rand::Rng;
+use rand::{seq::SliceRandom, Rng};
 use rand_chacha::ChaCha20Rng;
-use std::io::Error;
+use std::{
+    io::Error,
+    time::{Duration, Instant},
+};
+
+const CALIBRATION_SAMPLE_CNT: usize = 300;
+// acceptance probabilities of a typical worsening swap at the start and the end
+const INITIAL_ACCEPTANCE: f64 = 0.5;
+const FINAL_ACCEPTANCE: f64 = 0.01;
+
+/// Estimates start and end temperatures from the score changes of random swaps around a
+/// random arrangement of the students of `new` on `seats`, scored by `objective`. Returns `None`
+/// if no swap makes the score worse.
+pub fn calibrate_temperatures(
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    seats: &[(usize, usize)],
+    students: &[Student],
+    weights: &Weights,
+    objective: Objective,
+    rng: &mut ChaCha20Rng,
+) -> Result<Option<(f64, f64)>, Error> {
+    objective_score(previous, new, students, weights, objective)?;
+
+    if seats.len() < 2 {
+        return Ok(None);
+    }
+
+    let mut layout = new.clone();
+    let mut shuffled = seats
+        .iter()
+        .map(|&(x, y)| layout[y][x])
+        .collect::<Vec<usize>>();
+    shuffled.shuffle(rng);
+    for (&(x, y), &id) in seats.iter().zip(shuffled.iter()) {
+        layout[y][x] = id;
+    }
+    let base_score = objective_score(previous, &layout, students, weights, objective)?;
+
+    let mut deltas = vec![];
+    for _ in 0..CALIBRATION_SAMPLE_CNT {
+        let pos1 = seats[rng.gen_range(0..seats.len())];
+        let pos2 = seats[rng.gen_range(0..seats.len())];
+
+        swap_seats(&mut layout, pos1, pos2);
+        let score = objective_score(previous, &layout, students, weights, objective)?;
+        swap_seats(&mut layout, pos1, pos2);
+
+        if score < base_score {
+            deltas.push((base_score - score) as f64);
+        }
+    }
+    if deltas.is_empty() {
+        return Ok(None);
+    }
+
+    deltas.sort_by(f64::total_cmp);
+    let (typical, small) = (deltas[deltas.len() / 2], deltas[deltas.len() / 10]);
 
-const LOOP_CNT: usize = 200000;
-const T1: f64 = 119.5;
-const T2: f64 = 1.563;
+    Ok(Some((
+        typical / -INITIAL_ACCEPTANCE.ln(),
+        small / -FINAL_ACCEPTANCE.ln(),
+    )))
+}
+
+/// Temperature at `progress` (0.0 at the start, 1.0 at the end) of the annealing.
+pub fn temperature(schedule: TemperatureSchedule, t1: f64, t2: f64, progress: f64) -> f64 {
+    match schedule {
+        TemperatureSchedule::Linear => t1 + (t2 - t1) * progress,
+        TemperatureSchedule::Exponential => t1 * (t2 / t1).powf(progress),
+        TemperatureSchedule::Reheating { cycles } => {
+            t1 * (t2 / t1).powf((progress * cycles as f64).fract())
+        }
+    }
+}
 
+/// Moves the students of `new` between `seats`, see `propose`. Returns the new layout, its
+/// score and how often each move was proposed and accepted.
 pub fn execute(
-    previous: &SeatAssignment,
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    seats: &[(usize, usize)],
     students: &[Student],
-    loop_cnt: usize,
+    config: &Config,
     rng: &mut ChaCha20Rng,
-    temperture1: f64,
-    temperture2: f64,
-) -> Result<(SeatAssignment, i64), Error> {
-    let mut new = previous.clone();
-    let mut best_score = evaluate(previous, &new, students).unwrap();
-
-    let (depth, width) = (previous.len(), previous[0].len());
-
-    for i in 0..loop_cnt {
-        let (pos1, pos2) = (
-            (rng.gen_range(0..width), rng.gen_range(0..depth)),
-            (rng.gen_range(0..width), rng.gen_range(0..depth)),
-        );
+) -> Result<(SeatAssignment, i64, MoveStats), Error> {
+    objective_score(previous, new, students, &config.weights, config.objective)?;
+    let mut new = feasible_layout(
+        new,
+        seats,
+        &config.constraints,
+        config.spacing,
+        students.len(),
+    )?;
+    let rules = Rules::new(&config.constraints, new.len(), new[0].len(), students.len())
+        .with_spacing(config.spacing);
+    let mut best_score =
+        objective_score(previous, &new, students, &config.weights, config.objective)?;
 
-        if previous[pos1.1][pos1.0] == !0 || previous[pos2.1][pos2.0] == !0 {
-            continue;
+    let mut stats = MoveStats::new();
+
+    let calibrated = if config.auto_temperature {
+        calibrate_temperatures(
+            previous,
+            &new,
+            seats,
+            students,
+            &config.weights,
+            config.objective,
+            rng,
+        )?
+    } else {
+        None
+    };
+    let (temperture1, temperture2) =
+        calibrated.unwrap_or((config.temperature1, config.temperature2));
+
+    if ![temperture1, temperture2]
+        .iter()
+        .all(|t| t.is_finite() && *t > 0.0)
+    {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Temperatures must be positive and finite",
+        ));
+    }
+    if config.schedule == (TemperatureSchedule::Reheating { cycles: 0 }) {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Reheating schedule needs at least one cycle",
+        ));
+    }
+
+    let move_dist = move_distribution(&config.moves)?;
+    let worst_swap = config.moves.worst_swap > 0.0;
+    if worst_swap && config.objective != Objective::Regular {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Worst swaps are not supported by the exam objective",
+        ));
+    }
+    // pinned students never move
+    let seats = seats
+        .iter()
+        .copied()
+        .filter(|&(x, y)| new[y][x] == !0 || !rules.is_pinned(new[y][x]))
+        .collect::<Vec<(usize, usize)>>();
+
+    // individual scores for `Move::WorstSwap`, updated with the accepted moves
+    let context = if worst_swap {
+        Some(EvaluationContext::new(previous, students, &config.weights)?)
+    } else {
+        None
+    };
+    let mut evaluator = context
+        .as_ref()
+        .map(|context| IncrementalEvaluator::new(context, &new))
+        .transpose()?;
+
+    let start = Instant::now();
+    let time_limit = config.time_limit_ms.map(Duration::from_millis);
+
+    let mut i = 0;
+    loop {
+        let progress = match time_limit {
+            Some(time_limit) => start.elapsed().as_secs_f64() / time_limit.as_secs_f64(),
+            None => i as f64 / config.loop_cnt as f64,
+        };
+        if progress >= 1.0 || progress.is_nan() {
+            break;
         }
+        i += 1;
 
-        let temperture = temperture1 + (temperture2 - temperture1) * i as f64 / loop_cnt as f64;
+        let kind = sample_move(&move_dist, rng);
+        let individual_scores = evaluator.as_ref().map(|e| e.individual_scores());
+        let cycle = match propose(kind, &new, &seats, individual_scores, rng) {
+            Some(cycle) => cycle,
+            None => continue,
+        };
+        let stat = stats.entry(kind).or_default();
+        stat.proposed += 1;
 
-        swap_seats(&mut new, pos1, pos2);
+        let temperture = temperature(config.schedule, temperture1, temperture2, progress);
+
+        rotate(&mut new, &cycle);
+        if !cycle.iter().all(|&pos| rules.satisfied_at(&new, pos)) {
+            rotate_back(&mut new, &cycle);
+            continue;
+        }
 
-        if let Ok(new_score) = evaluate(previous, &new, students) {
-            let p = ((new_score - best_score) as f64 / temperture).exp();
+        if let Ok(new_score) =
+            objective_score(previous, &new, students, &config.weights, config.objective)
+        {
+            let p = ((new_score as f64 - best_score as f64) / temperture).exp();
             if new_score > best_score || rng.gen_bool(p) {
                 best_score = new_score;
+                stat.accepted += 1;
+                if let Some(evaluator) = evaluator.as_mut() {
+                    evaluator.rotate(&cycle);
+                }
             } else {
-                swap_seats(&mut new, pos1, pos2);
+                rotate_back(&mut new, &cycle);
             }
         } else {
-            swap_seats(&mut new, pos1, pos2);
-            return Err(Error::new(
-                std::io::ErrorKind::Other,
-                "eval_func() returned an error",
-            ));
+            rotate_back(&mut new, &cycle);
+            return Err(Error::other("eval_func() returned an error"));
         }
     }
 
-    Ok((new, best_score))
+    Ok((new, best_score, stats))
 }
 
+#[cfg(test)]
 mod tests {
     use rand::SeedableRng;
     use test::Bencher;
 
     use crate::{
-        eval_func::individual,
-        utils::{mean, standard_deviation, test_case},
+        eval_func::evaluate,
+        moves::occupied_seats,
+        scenario::{self, ScenarioConfig},
+        structs::{MoveWeights, TestVersions},
+        tuning,
+        utils::{compress_student_id, separate_input},
     };
 
     use super::*;
 
     #[test]
     fn score_test_simulated_annealing() {
+        let stats = tuning::run(&Config::default(), &ScenarioConfig::default(), 100, 123).unwrap();
+
+        println!("Mean: {}", stats.mean);
+        println!("Sigma: {}", stats.sigma);
+        println!("Mean(only individual): {}", stats.individual_mean);
+    }
+
+    #[test]
+    fn temperature_schedules() {
+        for schedule in [
+            TemperatureSchedule::Linear,
+            TemperatureSchedule::Exponential,
+            TemperatureSchedule::Reheating { cycles: 3 },
+        ] {
+            assert!((temperature(schedule, 100.0, 1.0, 0.0) - 100.0).abs() < 1e-9);
+            assert!(temperature(schedule, 100.0, 1.0, 0.3) < 100.0);
+            assert!(temperature(schedule, 100.0, 1.0, 0.3) > 1.0);
+        }
+
+        assert!((temperature(TemperatureSchedule::Linear, 100.0, 1.0, 0.5) - 50.5).abs() < 1e-9);
+        assert!(
+            (temperature(TemperatureSchedule::Exponential, 100.0, 1.0, 0.5) - 10.0).abs() < 1e-9
+        );
+        assert!(
+            (temperature(
+                TemperatureSchedule::Reheating { cycles: 2 },
+                100.0,
+                1.0,
+                0.5
+            ) - 100.0)
+                .abs()
+                < 1e-9
+        );
+    }
+
+    #[test]
+    fn calibrated_temperatures_are_decreasing() {
         let mut rng = ChaCha20Rng::seed_from_u64(123);
 
-        let mut scores = vec![];
-        let mut individual_scores = vec![];
-        for _ in 0..100 {
-            let (seat_assignment, students) = test_case(&mut rng);
-
-            let res = execute(&seat_assignment, &students, LOOP_CNT, &mut rng, T1, T2);
-            assert!(res.is_ok());
-            let individual_score_sum =
-                individual(&seat_assignment, &res.as_ref().unwrap().0, &students)
-                    .unwrap()
-                    .iter()
-                    .sum::<i64>() as f64;
-            scores.push(res.unwrap().1 as f64);
-            individual_scores.push(individual_score_sum as f64 / students.len() as f64);
+        for config in [
+            ScenarioConfig::default(),
+            ScenarioConfig {
+                depth: 7,
+                width: 7,
+                vacancy_ratio: 0.2,
+                ..Default::default()
+            },
+        ] {
+            let layout = scenario::generate(&config, &mut rng).unwrap();
+            let (mut seat_assignment, mut students) = separate_input(&layout);
+            compress_student_id(&mut students, &mut seat_assignment).unwrap();
+
+            let (t1, t2) = calibrate_temperatures(
+                &seat_assignment,
+                &seat_assignment,
+                &occupied_seats(&seat_assignment),
+                &students,
+                &Weights::default(),
+                Objective::Regular,
+                &mut rng,
+            )
+            .unwrap()
+            .unwrap();
+            assert!(t1 > t2 && t2 > 0.0);
+        }
+    }
+
+    #[test]
+    fn execute_with_schedules() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let layout = scenario::generate(&ScenarioConfig::default(), &mut rng).unwrap();
+        let (mut seat_assignment, mut students) = separate_input(&layout);
+        compress_student_id(&mut students, &mut seat_assignment).unwrap();
+
+        for (schedule, auto_temperature) in [
+            (TemperatureSchedule::Linear, true),
+            (TemperatureSchedule::Exponential, false),
+            (TemperatureSchedule::Exponential, true),
+            (TemperatureSchedule::Reheating { cycles: 4 }, true),
+        ] {
+            let config = Config {
+                loop_cnt: 20000,
+                schedule,
+                auto_temperature,
+                ..Default::default()
+            };
+            let initial_score = evaluate(
+                &seat_assignment,
+                &seat_assignment,
+                &students,
+                &config.weights,
+            )
+            .unwrap();
+
+            let (new, score, _) = execute(
+                &seat_assignment,
+                &seat_assignment,
+                &occupied_seats(&seat_assignment),
+                &students,
+                &config,
+                &mut rng,
+            )
+            .unwrap();
+            assert_eq!(
+                evaluate(&seat_assignment, &new, &students, &config.weights).unwrap(),
+                score
+            );
+            assert!(score > initial_score);
         }
 
-        let (score_mean, score_sigma) = (mean(&scores), standard_deviation(&scores));
+        let config = Config {
+            schedule: TemperatureSchedule::Reheating { cycles: 0 },
+            ..Default::default()
+        };
+        assert!(execute(
+            &seat_assignment,
+            &seat_assignment,
+            &occupied_seats(&seat_assignment),
+            &students,
+            &config,
+            &mut rng
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn execute_with_all_moves() {
+        let mut rng = ChaCha20Rng::seed_from_u64(123);
+        let scenario_config = ScenarioConfig {
+            vacancy_ratio: 0.2,
+            ..Default::default()
+        };
+        let layout = scenario::generate(&scenario_config, &mut rng).unwrap();
+        let (mut seat_assignment, mut students) = separate_input(&layout);
+        compress_student_id(&mut students, &mut seat_assignment).unwrap();
+
+        let config = Config {
+            loop_cnt: 20000,
+            moves: MoveWeights {
+                swap: 1.0,
+                cycle3: 1.0,
+                shift: 1.0,
+                neighbor_swap: 1.0,
+                worst_swap: 1.0,
+            },
+            ..Default::default()
+        };
+        let (new, score, stats) = execute(
+            &seat_assignment,
+            &seat_assignment,
+            &occupied_seats(&seat_assignment),
+            &students,
+            &config,
+            &mut rng,
+        )
+        .unwrap();
+
+        assert_eq!(
+            evaluate(&seat_assignment, &new, &students, &config.weights).unwrap(),
+            score
+        );
+        assert_eq!(occupied_seats(&new), occupied_seats(&seat_assignment));
+        assert_eq!(stats.len(), 5);
+        assert!(stats
+            .values()
+            .all(|s| s.proposed > 0 && s.accepted <= s.proposed));
+        assert!(stats.values().map(|s| s.proposed).sum::<usize>() <= config.loop_cnt);
 
-        println!("Mean: {}", score_mean);
-        println!("Sigma: {}", score_sigma);
-        println!("Mean(only individual): {}", mean(&individual_scores));
+        // worst swaps rank the students by their regular individual scores
+        let exam = Config {
+            objective: Objective::Exam {
+                versions: TestVersions::Checkerboard,
+            },
+            ..config
+        };
+        assert!(execute(
+            &seat_assignment,
+            &seat_assignment,
+            &occupied_seats(&seat_assignment),
+            &students,
+            &exam,
+            &mut rng,
+        )
+        .is_err());
     }
 
     #[bench]
     fn bench_simulated_annealing(b: &mut Bencher) {
         let mut rng = ChaCha20Rng::seed_from_u64(123);
 
-        let (seat_assignment, students) = test_case(&mut rng);
+        let layout = scenario::generate(&ScenarioConfig::default(), &mut rng).unwrap();
+        let (mut seat_assignment, mut students) = separate_input(&layout);
+        compress_student_id(&mut students, &mut seat_assignment).unwrap();
 
-        b.iter(|| execute(&seat_assignment, &students, LOOP_CNT, &mut rng, T1, T2))
+        b.iter(|| {
+            execute(
+                &seat_assignment,
+                &seat_assignment,
+                &occupied_seats(&seat_assignment),
+                &students,
+                &Config::default(),
+                &mut rng,
+            )
+        })
     }
 }
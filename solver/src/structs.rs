@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub struct Student {
     pub id: usize,
@@ -15,4 +17,329 @@ pub enum Gender {
     Female,
 }
 
-pub type SeatAssignment = Vec<Vec<usize>>;
\ No newline at end of file
+pub type SeatAssignment = Vec<Vec<usize>>;
+
+pub type Layout = Vec<Vec<Option<Student>>>;
+
+/// Layouts the students sat in before, the most recent one first. Students who sat next to each
+/// other in any of them are moved apart, twice as hard if they did so in two of them.
+pub trait PastLayouts {
+    fn layouts(&self) -> &[SeatAssignment];
+}
+
+impl PastLayouts for SeatAssignment {
+    fn layouts(&self) -> &[SeatAssignment] {
+        std::slice::from_ref(self)
+    }
+}
+
+impl PastLayouts for Vec<SeatAssignment> {
+    fn layouts(&self) -> &[SeatAssignment] {
+        self
+    }
+}
+
+/// Desks of a classroom, `room[y][x]` is whether there is a desk in column `x` of row `y`.
+pub type Room = Vec<Vec<bool>>;
+
+/// How students of a layout are moved into a room of another size, see `room::map_to_room`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum RoomMapping {
+    /// Students keep their column and row, counted from the front left.
+    #[default]
+    SeatCoordinates,
+    /// Students keep their position relative to the size of the room.
+    NearestPosition,
+}
+
+/// Hard constraint on the new layout. Seats are given by the column `x` and the row `y`,
+/// counted from the front left.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Constraint {
+    /// The student sits on the seat.
+    Pin { student: usize, x: usize, y: usize },
+    /// The two students do not sit next to each other, including diagonally.
+    Separate { students: [usize; 2] },
+    /// The student sits within the rows `rows[0]..=rows[1]` and the columns
+    /// `columns[0]..=columns[1]`.
+    Zone {
+        student: usize,
+        rows: [usize; 2],
+        columns: [usize; 2],
+    },
+}
+
+impl std::fmt::Display for Constraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Constraint::Pin { student, x, y } => {
+                write!(f, "student {} sits on seat ({}, {})", student, x, y)
+            }
+            Constraint::Separate { students } => write!(
+                f,
+                "students {} and {} do not sit next to each other",
+                students[0], students[1]
+            ),
+            Constraint::Zone {
+                student,
+                rows,
+                columns,
+            } => write!(
+                f,
+                "student {} sits within rows {}-{} and columns {}-{}",
+                student, rows[0], rows[1], columns[0], columns[1]
+            ),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Weights {
+    pub prev_adj_distance: f64,
+    pub blackboard_distance: f64,
+    pub academic: f64,
+    pub exercise: f64,
+    pub leadership: f64,
+    pub gender: f64,
+    /// Penalty of each pair of side by side students who write the same test in exam mode.
+    pub same_version: f64,
+    /// Penalty of each past layout in which a student sat in a zone (front, back, window or
+    /// aisle) of their new seat, once per zone. Off by default.
+    pub zone_repeat: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            prev_adj_distance: 1000.0,
+            blackboard_distance: 1000.0,
+            academic: 1000.0,
+            exercise: 1000.0,
+            leadership: 1000.0,
+            gender: 1000.0,
+            same_version: 1000.0,
+            zone_repeat: 0.0,
+        }
+    }
+}
+
+/// Terms of the score of a layout. The score is their sum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ScoreComponents {
+    /// Mean of the individual scores of the students.
+    pub individual: i64,
+    pub academic: i64,
+    pub exercise: i64,
+    pub leadership: i64,
+    pub gender: i64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum ScoreComponent {
+    Individual,
+    Academic,
+    Exercise,
+    Leadership,
+    Gender,
+}
+
+impl ScoreComponents {
+    /// Components which differ between the two scores.
+    pub fn changed(&self, other: &ScoreComponents) -> Vec<ScoreComponent> {
+        [
+            (
+                self.individual,
+                other.individual,
+                ScoreComponent::Individual,
+            ),
+            (self.academic, other.academic, ScoreComponent::Academic),
+            (self.exercise, other.exercise, ScoreComponent::Exercise),
+            (
+                self.leadership,
+                other.leadership,
+                ScoreComponent::Leadership,
+            ),
+            (self.gender, other.gender, ScoreComponent::Gender),
+        ]
+        .into_iter()
+        .filter(|(a, b, _)| a != b)
+        .map(|(_, _, component)| component)
+        .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Algorithm {
+    #[default]
+    SimulatedAnnealing,
+    BeamSearch,
+    TabuSearch,
+    /// Optimal layouts for classes of up to 12 students, simulated annealing for larger ones.
+    /// Rooms with more seats than students, vacant seats of the layout included, are also solved
+    /// by simulated annealing alone. Proving a layout of 12 students optimal can take tens of
+    /// seconds, see `time_limit_ms`.
+    BranchAndBound,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TemperatureSchedule {
+    /// Linear cooling from `temperature1` to `temperature2`.
+    #[default]
+    Linear,
+    /// Exponential cooling from `temperature1` to `temperature2`.
+    Exponential,
+    /// Exponential cooling restarted from `temperature1` `cycles` times.
+    Reheating { cycles: usize },
+}
+
+/// How the versions of a test are handed out in an exam.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum TestVersions {
+    /// Everyone writes the same test.
+    #[default]
+    Single,
+    /// Versions A and B alternate between the seats like the squares of a checkerboard.
+    Checkerboard,
+    /// Versions A and B alternate in the order of the attendance numbers (ids) of the students.
+    AttendanceOrder,
+}
+
+/// Which seats must be left vacant around each student when the room has more seats than
+/// students.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Spacing {
+    /// Any seats may be used.
+    #[default]
+    Any,
+    /// No two students side by side.
+    Horizontal,
+    /// No two students side by side or one behind the other.
+    Orthogonal,
+    /// No two students on neighbouring seats, diagonal ones included.
+    Surrounding,
+}
+
+impl Spacing {
+    /// Whether students may sit on both `a` and `b` at the same time.
+    pub fn allows(self, a: (usize, usize), b: (usize, usize)) -> bool {
+        let (dx, dy) = (a.0.abs_diff(b.0), a.1.abs_diff(b.1));
+        match self {
+            Spacing::Any => true,
+            Spacing::Horizontal => !(dx == 1 && dy == 0),
+            Spacing::Orthogonal => dx + dy != 1,
+            Spacing::Surrounding => dx > 1 || dy > 1 || (dx, dy) == (0, 0),
+        }
+    }
+}
+
+/// What the solver optimises.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum Objective {
+    /// The score of `eval_func::evaluate`.
+    #[default]
+    Regular,
+    /// Seats everyone far from the students they sat next to before and away from students
+    /// writing the same test, see `eval_func::evaluate_exam`.
+    Exam { versions: TestVersions },
+}
+
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum Move {
+    /// Swap two random students.
+    Swap,
+    /// Rotate three random students.
+    Cycle3,
+    /// Shift the students of a row or a column by one seat.
+    Shift,
+    /// Swap a student with one of the adjacent students.
+    NeighborSwap,
+    /// Swap the student with the lowest individual score with a random student. Not supported
+    /// by the exam objective.
+    WorstSwap,
+}
+
+/// Relative frequencies of the moves proposed by simulated annealing.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct MoveWeights {
+    pub swap: f64,
+    pub cycle3: f64,
+    pub shift: f64,
+    pub neighbor_swap: f64,
+    pub worst_swap: f64,
+}
+
+impl Default for MoveWeights {
+    fn default() -> Self {
+        MoveWeights {
+            swap: 1.0,
+            cycle3: 0.0,
+            shift: 0.0,
+            neighbor_swap: 0.0,
+            worst_swap: 0.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct MoveStat {
+    pub proposed: usize,
+    pub accepted: usize,
+}
+
+pub type MoveStats = BTreeMap<Move, MoveStat>;
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Seed of the random number generator. Derived from the input when `None`.
+    pub seed: Option<u64>,
+    pub algorithm: Algorithm,
+    pub loop_cnt: usize,
+    /// Time budget in milliseconds. Simulated annealing runs for exactly this long and
+    /// ignores `loop_cnt`, the other algorithms stop early once it is exceeded.
+    pub time_limit_ms: Option<u64>,
+    pub temperature1: f64,
+    pub temperature2: f64,
+    /// Choose the temperatures of simulated annealing from score changes of random swaps
+    /// instead of `temperature1` and `temperature2`.
+    pub auto_temperature: bool,
+    pub schedule: TemperatureSchedule,
+    pub moves: MoveWeights,
+    /// Number of layouts kept at each step of beam search.
+    pub beam_width: usize,
+    pub weights: Weights,
+    /// Hard constraints, supported by simulated annealing and branch and bound. Students are
+    /// given by the ids of the input of `execute`, which are the indices of `students` for
+    /// `solve`.
+    pub constraints: Vec<Constraint>,
+    /// Supported by simulated annealing only.
+    pub objective: Objective,
+    /// Vacant seats to keep between the students, who choose among the vacant seats of the room
+    /// while they move. Supported by simulated annealing only.
+    pub spacing: Spacing,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            seed: None,
+            algorithm: Algorithm::SimulatedAnnealing,
+            loop_cnt: 200000,
+            time_limit_ms: None,
+            temperature1: 119.5,
+            temperature2: 1.563,
+            auto_temperature: false,
+            schedule: TemperatureSchedule::Linear,
+            moves: MoveWeights::default(),
+            beam_width: 10,
+            weights: Weights::default(),
+            constraints: vec![],
+            objective: Objective::Regular,
+            spacing: Spacing::Any,
+        }
+    }
+}
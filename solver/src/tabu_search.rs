@@ -1,51 +1,74 @@
 use crate::{
     eval_func::evaluate,
-    structs::{SeatAssignment, Student},
+    structs::{Config, PastLayouts, SeatAssignment, Student},
     utils::swap_seats,
 };
 
-use std::{collections::{BTreeSet, VecDeque}, io::Error};
+use std::{
+    collections::{BTreeSet, VecDeque},
+    io::Error,
+    time::{Duration, Instant},
+};
+
+const NEIGHBOR_CNT: usize = 100;
+const TABU_LIST_SIZE: usize = 10;
 
+/// Swaps the students of `new` between `seats`, trying `NEIGHBOR_CNT` swaps per iteration.
 pub fn execute<R: rand::Rng>(
-    previous: &SeatAssignment,
+    previous: &dyn PastLayouts,
+    new: &SeatAssignment,
+    seats: &[(usize, usize)],
     students: &[Student],
-    loop_cnt: usize,
-    neighbor_cnt: usize,
-    tabu_list_size: usize,
+    config: &Config,
     rng: &mut R,
 ) -> Result<(SeatAssignment, i64), Error> {
     let mut tabu_list = BTreeSet::new();
     let mut deq = VecDeque::new();
 
-    let mut current = previous.clone();
-
-    let mut best = evaluate(previous, &current, students).unwrap();
-    
-    for _ in 0..loop_cnt {
-        for _ in 0..neighbor_cnt {
-            let (pos1, pos2) = loop {
-                let pos1 = (rng.gen_range(0..previous.len()), rng.gen_range(0..previous[0].len()));
-                let pos2 = (rng.gen_range(0..previous.len()), rng.gen_range(0..previous[0].len()));
-                if pos1 != pos2 && !tabu_list.contains(&(students[previous[pos1.0][pos1.1]].id, students[previous[pos2.0][pos2.1]].id)) {
-                    break (pos1, pos2);
-                }
-            };
+    let mut current = new.clone();
+
+    let mut best = evaluate(previous, &current, students, &config.weights)?;
+
+    if seats.is_empty() {
+        return Ok((current, best));
+    }
+
+    let deadline = config
+        .time_limit_ms
+        .map(|ms| Instant::now() + Duration::from_millis(ms));
+
+    for _ in 0..config.loop_cnt / NEIGHBOR_CNT {
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            break;
+        }
+
+        for _ in 0..NEIGHBOR_CNT {
+            let (pos1, pos2) = (
+                seats[rng.gen_range(0..seats.len())],
+                seats[rng.gen_range(0..seats.len())],
+            );
+            let (id1, id2) = (current[pos1.1][pos1.0], current[pos2.1][pos2.0]);
+
+            if id1 == id2 || tabu_list.contains(&(id1, id2)) {
+                continue;
+            }
 
             swap_seats(&mut current, pos1, pos2);
-            let score = evaluate(previous, &current, students).unwrap();
-            if score < best {
+            let score = evaluate(previous, &current, students, &config.weights)?;
+            if score > best {
                 best = score;
             } else {
                 swap_seats(&mut current, pos1, pos2);
                 continue;
             }
 
-            tabu_list.insert((students[previous[pos1.0][pos1.1]].id, students[previous[pos2.0][pos2.1]].id));
-            deq.push_back((students[previous[pos1.0][pos1.1]].id, students[previous[pos2.0][pos2.1]].id));
+            tabu_list.insert((id1, id2));
+            deq.push_back((id1, id2));
 
-            while deq.len() > tabu_list_size {
-                let (id1, id2) = deq.pop_front().unwrap();
-                tabu_list.remove(&(id1, id2));
+            while deq.len() > TABU_LIST_SIZE {
+                if let Some(pair) = deq.pop_front() {
+                    tabu_list.remove(&pair);
+                }
             }
         }
     }
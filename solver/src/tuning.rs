@@ -0,0 +1,74 @@
+use crate::{
+    eval_func::individual,
+    scenario::{self, ScenarioConfig},
+    solve_with_stats,
+    structs::{Config, MoveStats},
+    utils::{compress_student_id, mean, separate_input, standard_deviation},
+};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use std::{io::Error, time::Instant};
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TuningStats {
+    pub case_cnt: usize,
+    pub mean: f64,
+    pub sigma: f64,
+    /// Mean of the per-student individual scores, i.e. the score without the balance terms.
+    pub individual_mean: f64,
+    pub runtime_ms: f64,
+    /// Move statistics of simulated annealing summed over all cases.
+    pub moves: MoveStats,
+}
+
+/// Solves `case_cnt` test cases generated from `scenario` with `config` and summarises the
+/// scores.
+///
+/// Every case gets its own solver seed drawn from `seed`, so the statistics are reproducible
+/// for a given `seed` regardless of `config.seed`.
+pub fn run(
+    config: &Config,
+    scenario: &ScenarioConfig,
+    case_cnt: usize,
+    seed: u64,
+) -> Result<TuningStats, Error> {
+    let mut rng = ChaCha20Rng::seed_from_u64(seed);
+
+    let mut scores = vec![];
+    let mut individual_scores = vec![];
+    let mut moves = MoveStats::new();
+    let start = Instant::now();
+    for _ in 0..case_cnt {
+        let layout = scenario::generate(scenario, &mut rng)?;
+        let (mut seat_assignment, mut students) = separate_input(&layout);
+        compress_student_id(&mut students, &mut seat_assignment)?;
+
+        let case_config = Config {
+            seed: Some(rng.gen()),
+            ..config.clone()
+        };
+        let (new, score, case_moves) = solve_with_stats(&seat_assignment, &students, &case_config)?;
+        for (kind, stat) in case_moves {
+            let total = moves.entry(kind).or_default();
+            total.proposed += stat.proposed;
+            total.accepted += stat.accepted;
+        }
+
+        let individual_score_sum = individual(&seat_assignment, &new, &students, &config.weights)?
+            .iter()
+            .sum::<i64>() as f64;
+        scores.push(score as f64);
+        individual_scores.push(individual_score_sum / students.len() as f64);
+    }
+    let runtime_ms = start.elapsed().as_secs_f64() * 1000.0;
+
+    Ok(TuningStats {
+        case_cnt,
+        mean: mean(&scores),
+        sigma: standard_deviation(&scores),
+        individual_mean: mean(&individual_scores),
+        runtime_ms,
+        moves,
+    })
+}
@@ -1,8 +1,4 @@
-use rand::seq::SliceRandom;
-use rand_chacha::ChaCha20Rng;
-use rand_distr::{Distribution, Normal};
-
-use crate::structs::{Gender, SeatAssignment, Student};
+use crate::structs::{Layout, SeatAssignment, Student};
 
 use std::{collections::HashSet, io::Error};
 
@@ -35,7 +31,29 @@ pub fn separate_input(input: &[Vec<Option<Student>>]) -> (SeatAssignment, Vec<St
     (idx_seat_assignment, students)
 }
 
+pub fn layout_size<T>(layout: &[Vec<T>]) -> Result<(usize, usize), Error> {
+    let (depth, width) = (layout.len(), layout.first().map_or(0, |row| row.len()));
+
+    if depth == 0 || width == 0 {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Layout is empty",
+        ));
+    }
+
+    if layout.iter().any(|row| row.len() != width) {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "Layout rows have different lengths",
+        ));
+    }
+
+    Ok((depth, width))
+}
+
 pub fn check_input(input: &[Vec<Option<Student>>]) -> Result<(), Error> {
+    layout_size(input)?;
+
     let studnet_ids = input
         .iter()
         .flatten()
@@ -43,18 +61,22 @@ pub fn check_input(input: &[Vec<Option<Student>>]) -> Result<(), Error> {
         .map(|s| s.as_ref().unwrap().id)
         .collect::<Vec<usize>>();
 
+    check_unique_ids(&studnet_ids)
+}
+
+pub fn check_unique_ids(student_ids: &[usize]) -> Result<(), Error> {
     let mut id_set = HashSet::new();
     let mut duplicated_ids = vec![];
 
-    for &id in studnet_ids.iter() {
+    for &id in student_ids.iter() {
         if id_set.contains(&id) {
             duplicated_ids.push(id);
         }
         id_set.insert(id);
     }
 
-    duplicated_ids.dedup();
     duplicated_ids.sort();
+    duplicated_ids.dedup();
 
     if !duplicated_ids.is_empty() {
         return Err(Error::new(
@@ -66,7 +88,10 @@ pub fn check_input(input: &[Vec<Option<Student>>]) -> Result<(), Error> {
     Ok(())
 }
 
-pub fn compress_student_id(students: &mut [Student], idx_layout: &mut SeatAssignment) {
+pub fn compress_student_id(
+    students: &mut [Student],
+    idx_layout: &mut SeatAssignment,
+) -> Result<(), Error> {
     let student_ids = students.iter().map(|s| s.id).collect::<Vec<usize>>();
 
     let sorted_student_ids = {
@@ -76,58 +101,120 @@ pub fn compress_student_id(students: &mut [Student], idx_layout: &mut SeatAssign
     };
 
     for student in students.iter_mut() {
-        let idx = sorted_student_ids
-            .binary_search(&student.id)
-            .expect("Student id not found in sorted ids");
-
-        student.id = idx;
+        student.id = find_compressed_id(&sorted_student_ids, student.id)?;
     }
 
     students.sort_by_key(|s| s.id);
 
-    for y in 0..idx_layout.len() {
-        for x in 0..idx_layout[y].len() {
-            if idx_layout[y][x] != !0 {
-                let idx = student_ids
-                    .binary_search(&idx_layout[y][x])
-                    .expect("Student id not found in sorted ids");
+    for seat in idx_layout.iter_mut().flatten() {
+        if *seat != !0 {
+            *seat = find_compressed_id(&sorted_student_ids, *seat)?;
+        }
+    }
+
+    Ok(())
+}
 
-                idx_layout[y][x] = idx;
+/// Layouts of `history` with the indices of the students in the sorted `student_ids`. Students
+/// who are not in `student_ids` become vacant seats.
+pub fn compress_history(
+    history: &[Layout],
+    student_ids: &[usize],
+) -> Result<Vec<SeatAssignment>, Error> {
+    let mut compressed = vec![];
+    for layout in history.iter() {
+        check_input(layout)?;
+        let (mut layout, _) = separate_input(layout);
+        for id in layout.iter_mut().flatten() {
+            *id = student_ids.binary_search(id).unwrap_or(!0);
+        }
+        compressed.push(layout);
+    }
+
+    Ok(compressed)
+}
+
+fn find_compressed_id(sorted_student_ids: &[usize], id: usize) -> Result<usize, Error> {
+    sorted_student_ids.binary_search(&id).map_err(|_| {
+        Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("Student id {} not found in sorted ids", id),
+        )
+    })
+}
+
+/// Desks of `room`, front row first.
+pub fn room_seats(room: &[Vec<bool>]) -> Vec<(usize, usize)> {
+    let mut seats = vec![];
+    for (y, row) in room.iter().enumerate() {
+        for (x, &desk) in row.iter().enumerate() {
+            if desk {
+                seats.push((x, y));
             }
         }
     }
+    seats
 }
 
-pub fn swap_seats(assigment: &mut SeatAssignment, pos1: (usize, usize), pos2: (usize, usize)) {
-    let tmp = assigment[pos1.1][pos1.0];
-    assigment[pos1.1][pos1.0] = assigment[pos2.1][pos2.0];
-    assigment[pos2.1][pos2.0] = tmp;
+/// Seats of the 3x3 neighbourhood of `(x, y)` in a `depth` x `width` layout, including the seat
+/// itself.
+pub fn neighbourhood(
+    (x, y): (usize, usize),
+    depth: usize,
+    width: usize,
+) -> impl Iterator<Item = (usize, usize)> {
+    (y.saturating_sub(1)..(y + 2).min(depth))
+        .flat_map(move |y2| (x.saturating_sub(1)..(x + 2).min(width)).map(move |x2| (x2, y2)))
 }
 
-pub fn test_case(rng: &mut ChaCha20Rng) -> (SeatAssignment, Vec<Student>) {
-    let normal = Normal::<f64>::new(3.0, 1.0).unwrap();
-
-    let students = (0..30)
-        .map(|i| Student {
-            id: i,
-            name: format!("Student {}", i),
-            academic_ability: (normal.sample(rng).round() as usize).max(1).min(5),
-            exercise_ability: (normal.sample(rng).round() as usize).max(1).min(5),
-            leadership_ability: (normal.sample(rng).round() as usize).max(1).min(5),
-            needs_assistance: i < 3,
-            gender: if i < 15 { Gender::Male } else { Gender::Female },
-        })
-        .collect::<Vec<Student>>();
-
-    let mut seat_assignment = vec![vec![!0; 6]; 5];
-    let mut student_ids = (0..30).collect::<Vec<usize>>();
-    student_ids.shuffle(rng);
-    for i in 0..30 {
-        let (x, y) = (i % 6, i / 6);
-        seat_assignment[y][x] = student_ids[i];
+/// Seats the `n` students in `room`. Students keep their seat in `previous` if it is a desk of
+/// `room`, the others take the free desks front row first.
+pub fn initial_layout(
+    previous: &[Vec<usize>],
+    room: &[Vec<bool>],
+    n: usize,
+) -> Result<SeatAssignment, Error> {
+    let (depth, width) = layout_size(room)?;
+    let seats = room_seats(room);
+    if seats.len() < n {
+        return Err(Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("The room has {} seats for {} students", seats.len(), n),
+        ));
+    }
+
+    let mut new = vec![vec![!0; width]; depth];
+    let mut seated = vec![false; n];
+    for y in 0..previous.len().min(depth) {
+        for x in 0..previous[y].len().min(width) {
+            let id = previous[y][x];
+            if id < n && !seated[id] && room[y][x] {
+                new[y][x] = id;
+                seated[id] = true;
+            }
+        }
     }
 
-    (seat_assignment, students)
+    let mut free = seats
+        .into_iter()
+        .filter(|&(x, y)| new[y][x] == !0)
+        .collect::<Vec<(usize, usize)>>()
+        .into_iter();
+    for (i, &seated) in seated.iter().enumerate() {
+        if !seated {
+            // there are at least as many seats as students
+            let (x, y) = free.next().unwrap();
+            new[y][x] = i;
+        }
+    }
+
+    Ok(new)
+}
+
+pub fn swap_seats(assigment: &mut SeatAssignment, pos1: (usize, usize), pos2: (usize, usize)) {
+    let tmp = assigment[pos1.1][pos1.0];
+    assigment[pos1.1][pos1.0] = assigment[pos2.1][pos2.0];
+    assigment[pos2.1][pos2.0] = tmp;
 }
 
 pub fn mean(values: &[f64]) -> f64 {
@@ -141,6 +228,7 @@ pub fn standard_deviation(values: &[f64]) -> f64 {
     variance.sqrt()
 }
 
+#[cfg(test)]
 mod tests {
     use crate::structs::Gender;
 
@@ -167,6 +255,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_initial_layout() {
+        let previous = vec![vec![0, 1, 2], vec![3, !0, 4]];
+        // the back row and the left column were removed and a desk was added at the right
+        let room = vec![vec![false, true, true, true]];
+
+        let new = initial_layout(&previous, &room, 3).unwrap();
+        assert_eq!(new, vec![vec![!0, 1, 2, 0]]);
+        assert!(initial_layout(&previous, &room, 4).is_err());
+        assert!(initial_layout(&previous, &[], 0).is_err());
+    }
+
     #[test]
     fn test_compress_student_id() {
         let mut students1 = (1..=15)
@@ -201,7 +301,7 @@ mod tests {
             layout1_want[i / 4][i % 4] = student.id;
         }
 
-        compress_student_id(&mut students1, &mut layout1);
+        compress_student_id(&mut students1, &mut layout1).unwrap();
         assert_eq!(students1, students1_want);
         assert_eq!(layout1, layout1_want);
     }
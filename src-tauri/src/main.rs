@@ -1,8 +1,30 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use solver::structs::Student;
-use tauri::{CustomMenuItem, Menu, MenuItem, Submenu};
+use solver::{
+    diff::LayoutDiff,
+    explain::{Explanation, SwapScore},
+    export::ExportFormat,
+    import::{ColumnMapping, Roster},
+    project::Project,
+    structs::{Config, Objective, RoomMapping, Spacing, Student, TestVersions},
+};
+use std::{
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::{CustomMenuItem, Menu, MenuItem, State, Submenu};
+
+/// Project that is being edited, including the history of its layouts.
+#[derive(Default)]
+struct ProjectStore(Mutex<Project>);
+
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct HistoryItem {
+    index: usize,
+    timestamp: u64,
+    label: String,
+}
 
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 struct ExecutionResult {
@@ -10,8 +32,41 @@ struct ExecutionResult {
     score: i64,
 }
 
+/// Layouts of the history, the most recent one first, with the edited layout in place of the
+/// current layout.
+fn past_layouts(
+    store: &State<ProjectStore>,
+    current_seat_assignment: &[Vec<Option<Student>>],
+) -> Vec<Vec<Vec<Option<Student>>>> {
+    let mut history = store.0.lock().unwrap().past_layouts();
+    match history.first_mut() {
+        Some(current) => *current = current_seat_assignment.to_vec(),
+        None => history.push(current_seat_assignment.to_vec()),
+    }
+
+    history
+}
+
+/// Layouts of the history before the current layout, the most recent one first.
+fn layouts_before_current(store: &State<ProjectStore>) -> Vec<Vec<Vec<Option<Student>>>> {
+    let mut history = store.0.lock().unwrap().past_layouts();
+    if !history.is_empty() {
+        history.remove(0);
+    }
+
+    history
+}
+
+/// Solver settings of the project, used by every command that solves or scores a layout.
+fn settings(store: &State<ProjectStore>) -> Config {
+    store.0.lock().unwrap().settings.clone()
+}
+
 #[tauri::command]
-fn solve(current_seat_assignment: Vec<Vec<Option<Student>>>) -> Result<ExecutionResult, String> {
+fn solve(
+    current_seat_assignment: Vec<Vec<Option<Student>>>,
+    store: State<ProjectStore>,
+) -> Result<ExecutionResult, String> {
     if current_seat_assignment
         .iter()
         .flatten()
@@ -20,7 +75,32 @@ fn solve(current_seat_assignment: Vec<Vec<Option<Student>>>) -> Result<Execution
         return Err("席が空です。".to_string());
     }
 
-    let solver_res = solver::execute(&current_seat_assignment);
+    let config = settings(&store);
+    let history = past_layouts(&store, &current_seat_assignment);
+    if history.len() > 1 {
+        let roster = current_seat_assignment
+            .iter()
+            .flatten()
+            .flatten()
+            .cloned()
+            .collect::<Vec<Student>>();
+        let room = current_seat_assignment
+            .iter()
+            .map(|row| row.iter().map(|seat| seat.is_some()).collect())
+            .collect::<Vec<Vec<bool>>>();
+
+        let (new_seat_assignment, score) =
+            solver::execute_with_history(&history, &roster, &room, &config)
+                .map_err(|err| format!("Solver error: {:?}", err))?;
+
+        return Ok(ExecutionResult {
+            new_seat_assignment,
+            score,
+        });
+    }
+
+    // without earlier layouts there are no zones to rotate through
+    let solver_res = solver::execute_with_config(&current_seat_assignment, &config);
 
     if solver_res.is_err() {
         return Err(format!("Solver error: {:?}", solver_res.err()));
@@ -35,21 +115,311 @@ fn solve(current_seat_assignment: Vec<Vec<Option<Student>>>) -> Result<Execution
 }
 
 #[tauri::command]
-fn gen_pdf_bytes(seat_assignment: Vec<Vec<Option<Student>>>) -> Result<Vec<u8>, String> {
-    let seats = seat_assignment
+fn solve_from_roster(
+    roster: Vec<Student>,
+    width: usize,
+    depth: usize,
+    spacing: Option<Spacing>,
+    store: State<ProjectStore>,
+) -> Result<ExecutionResult, String> {
+    if roster.is_empty() {
+        return Err("名簿が空です。".to_string());
+    }
+
+    let settings = settings(&store);
+    let config = Config {
+        spacing: spacing.unwrap_or(settings.spacing),
+        ..settings
+    };
+    let (new_seat_assignment, score) =
+        solver::execute_from_roster(&roster, &vec![vec![true; width]; depth], &config)
+            .map_err(|err| format!("Solver error: {:?}", err))?;
+
+    Ok(ExecutionResult {
+        new_seat_assignment,
+        score,
+    })
+}
+
+#[tauri::command]
+fn import_roster(path: String, mapping: Option<ColumnMapping>) -> Result<Roster, String> {
+    let bytes = std::fs::read(&path).map_err(|err| format!("File error: {:?}", err))?;
+
+    solver::import::import_csv(&bytes, &mapping.unwrap_or_default())
+        .map_err(|err| format!("Import error: {:?}", err))
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Saves the project with `seat_assignment` as its current layout, along with the room and the
+/// roster of the layout.
+#[tauri::command]
+fn save_project(
+    path: String,
+    seat_assignment: Vec<Vec<Option<Student>>>,
+    store: State<ProjectStore>,
+) -> Result<(), String> {
+    let mut project = store.0.lock().unwrap();
+    project.set_current(seat_assignment, now());
+
+    let text =
+        solver::project::save(&project).map_err(|err| format!("Project error: {:?}", err))?;
+
+    std::fs::write(&path, text).map_err(|err| format!("File error: {:?}", err))
+}
+
+#[tauri::command]
+fn load_project(path: String, store: State<ProjectStore>) -> Result<Project, String> {
+    let text = std::fs::read_to_string(&path).map_err(|err| format!("File error: {:?}", err))?;
+
+    let project =
+        solver::project::load(&text).map_err(|err| format!("Project error: {:?}", err))?;
+    *store.0.lock().unwrap() = project.clone();
+
+    Ok(project)
+}
+
+#[tauri::command]
+fn list_history(store: State<ProjectStore>) -> Vec<HistoryItem> {
+    let project = store.0.lock().unwrap();
+
+    project
+        .history
         .iter()
-        .map(|row| {
-            row.iter()
-                .map(|seat| {
-                    if let Some(student) = seat {
-                        format!("{}. {}", student.id, &student.name)
-                    } else {
-                        "".to_string()
-                    }
-                })
-                .collect::<Vec<String>>()
+        .enumerate()
+        .map(|(index, entry)| HistoryItem {
+            index,
+            timestamp: entry.timestamp,
+            label: entry.label.clone(),
         })
-        .collect::<Vec<Vec<String>>>();
+        .collect()
+}
+
+/// Adds `new_seat_assignment` to the history as the current layout, after the edited layout it
+/// was solved from.
+#[tauri::command]
+fn record_history(
+    label: String,
+    current_seat_assignment: Vec<Vec<Option<Student>>>,
+    new_seat_assignment: Vec<Vec<Option<Student>>>,
+    store: State<ProjectStore>,
+) -> Result<(), String> {
+    if new_seat_assignment.iter().flatten().all(|x| x.is_none()) {
+        return Err("席が空です。".to_string());
+    }
+
+    let mut project = store.0.lock().unwrap();
+    project.set_current(current_seat_assignment, now());
+    project.record(new_seat_assignment, &label, now());
+
+    Ok(())
+}
+
+#[tauri::command]
+fn restore_history(
+    index: usize,
+    store: State<ProjectStore>,
+) -> Result<Vec<Vec<Option<Student>>>, String> {
+    let mut project = store.0.lock().unwrap();
+
+    project
+        .restore(index, now())
+        .map(|entry| entry.layout.clone())
+        .map_err(|err| format!("History error: {:?}", err))
+}
+
+#[tauri::command]
+fn diff_history(from: usize, to: usize, store: State<ProjectStore>) -> Result<LayoutDiff, String> {
+    let project = store.0.lock().unwrap();
+
+    let (from, to) = match (project.history.get(from), project.history.get(to)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return Err("履歴が見つかりません。".to_string()),
+    };
+
+    solver::diff::diff(&from.layout, &to.layout).map_err(|err| format!("Diff error: {:?}", err))
+}
+
+/// Error of `resize_layout`, so that the app can tell a size with too few seats from other
+/// errors.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "kind", content = "message")]
+enum ResizeError {
+    TooFewSeats,
+    Other(String),
+}
+
+#[tauri::command]
+fn resize_layout(
+    seat_assignment: Vec<Vec<Option<Student>>>,
+    width: usize,
+    depth: usize,
+) -> Result<Vec<Vec<Option<Student>>>, ResizeError> {
+    if seat_assignment.iter().flatten().flatten().count() > width * depth {
+        return Err(ResizeError::TooFewSeats);
+    }
+
+    solver::room::map_to_room(
+        &seat_assignment,
+        &vec![vec![true; width]; depth],
+        RoomMapping::SeatCoordinates,
+    )
+    .map_err(|err| ResizeError::Other(format!("Solver error: {:?}", err)))
+}
+
+#[tauri::command]
+fn export_layout(
+    previous_seat_assignment: Vec<Vec<Option<Student>>>,
+    new_seat_assignment: Vec<Vec<Option<Student>>>,
+    format: ExportFormat,
+) -> Result<String, String> {
+    solver::export::export(&previous_seat_assignment, &new_seat_assignment, format)
+        .map_err(|err| format!("Export error: {:?}", err))
+}
+
+/// How often each student sat in the front, back, window and aisle zones over the history and
+/// the new layout.
+#[tauri::command]
+fn export_zone_report(
+    current_seat_assignment: Vec<Vec<Option<Student>>>,
+    new_seat_assignment: Vec<Vec<Option<Student>>>,
+    format: ExportFormat,
+    store: State<ProjectStore>,
+) -> Result<String, String> {
+    let mut layouts = past_layouts(&store, &current_seat_assignment);
+    layouts.insert(0, new_seat_assignment);
+
+    solver::rotation::export_zone_report(&layouts, format)
+        .map_err(|err| format!("Export error: {:?}", err))
+}
+
+#[tauri::command]
+fn diff_layouts(
+    previous_seat_assignment: Vec<Vec<Option<Student>>>,
+    new_seat_assignment: Vec<Vec<Option<Student>>>,
+) -> Result<LayoutDiff, String> {
+    solver::diff::diff(&previous_seat_assignment, &new_seat_assignment)
+        .map_err(|err| format!("Diff error: {:?}", err))
+}
+
+/// Explains the seat of a student in the new layout solved from the edited layout.
+#[tauri::command]
+fn explain_placement(
+    current_seat_assignment: Vec<Vec<Option<Student>>>,
+    new_seat_assignment: Vec<Vec<Option<Student>>>,
+    student_id: usize,
+    store: State<ProjectStore>,
+) -> Result<Explanation, String> {
+    let history = past_layouts(&store, &current_seat_assignment);
+
+    solver::explain::explain(
+        &history,
+        &new_seat_assignment,
+        student_id,
+        &settings(&store),
+    )
+    .map_err(|err| format!("Solver error: {:?}", err))
+}
+
+/// Scores swapping the seats `a` and `b`, given as `[x, y]`, of the layout being edited against
+/// the layouts before it.
+#[tauri::command]
+fn score_swap(
+    seat_assignment: Vec<Vec<Option<Student>>>,
+    a: [usize; 2],
+    b: [usize; 2],
+    store: State<ProjectStore>,
+) -> Result<SwapScore, String> {
+    let history = layouts_before_current(&store);
+
+    solver::explain::what_if_swap(
+        &history,
+        &seat_assignment,
+        (a[0], a[1]),
+        (b[0], b[1]),
+        &settings(&store),
+    )
+    .map_err(|err| format!("Solver error: {:?}", err))
+}
+
+/// Re-optimises the edited layout around the students on the `fixed` seats, given as `[x, y]`.
+#[tauri::command]
+fn repair_layout(
+    seat_assignment: Vec<Vec<Option<Student>>>,
+    fixed: Vec<[usize; 2]>,
+    store: State<ProjectStore>,
+) -> Result<ExecutionResult, String> {
+    if seat_assignment.iter().flatten().all(|x| x.is_none()) {
+        return Err("席が空です。".to_string());
+    }
+
+    let history = layouts_before_current(&store);
+    let fixed = fixed
+        .iter()
+        .map(|&[x, y]| (x, y))
+        .collect::<Vec<(usize, usize)>>();
+
+    let (new_seat_assignment, score) =
+        solver::repair(&history, &seat_assignment, &fixed, &settings(&store))
+            .map_err(|err| format!("Solver error: {:?}", err))?;
+
+    Ok(ExecutionResult {
+        new_seat_assignment,
+        score,
+    })
+}
+
+#[tauri::command]
+fn solve_exam(
+    current_seat_assignment: Vec<Vec<Option<Student>>>,
+    versions: TestVersions,
+    store: State<ProjectStore>,
+) -> Result<ExecutionResult, String> {
+    if current_seat_assignment
+        .iter()
+        .flatten()
+        .all(|x| x.is_none())
+    {
+        return Err("席が空です。".to_string());
+    }
+
+    let history = past_layouts(&store, &current_seat_assignment);
+    let roster = current_seat_assignment
+        .iter()
+        .flatten()
+        .flatten()
+        .cloned()
+        .collect::<Vec<Student>>();
+    let room = current_seat_assignment
+        .iter()
+        .map(|row| row.iter().map(|seat| seat.is_some()).collect())
+        .collect::<Vec<Vec<bool>>>();
+    let config = Config {
+        objective: Objective::Exam { versions },
+        ..settings(&store)
+    };
+
+    let (new_seat_assignment, score) =
+        solver::execute_with_history(&history, &roster, &room, &config)
+            .map_err(|err| format!("Solver error: {:?}", err))?;
+
+    Ok(ExecutionResult {
+        new_seat_assignment,
+        score,
+    })
+}
+
+#[tauri::command]
+fn gen_pdf_bytes(
+    seat_assignment: Vec<Vec<Option<Student>>>,
+    versions: Option<TestVersions>,
+) -> Result<Vec<u8>, String> {
+    let seats = solver::export::seat_labels(&seat_assignment, versions.unwrap_or_default());
 
     let bytes = pdf_generator::gen(seats);
 
@@ -73,6 +443,7 @@ fn main() {
         .add_submenu(edit);
 
     tauri::Builder::default()
+        .manage(ProjectStore::default())
         .menu(menu)
         .on_menu_event(|event| match event.menu_item_id() {
             "change_size" => {
@@ -89,7 +460,26 @@ fn main() {
             }
             _ => {}
         })
-        .invoke_handler(tauri::generate_handler![solve, gen_pdf_bytes])
+        .invoke_handler(tauri::generate_handler![
+            solve,
+            solve_from_roster,
+            import_roster,
+            save_project,
+            load_project,
+            list_history,
+            record_history,
+            restore_history,
+            diff_history,
+            resize_layout,
+            export_layout,
+            export_zone_report,
+            diff_layouts,
+            explain_placement,
+            score_swap,
+            repair_layout,
+            solve_exam,
+            gen_pdf_bytes
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
@@ -2,7 +2,7 @@ import { useEffect, useState } from "react";
 import { invoke } from "@tauri-apps/api/tauri";
 import { listen } from "@tauri-apps/api/event";
 import { save, open, confirm, message } from "@tauri-apps/api/dialog";
-import { writeTextFile, readTextFile, writeBinaryFile } from "@tauri-apps/api/fs";
+import { writeTextFile, writeBinaryFile } from "@tauri-apps/api/fs";
 
 import { Box, Drawer, Grid, Stack, TextField, Divider, Typography, InputLabel, Select, MenuItem, Checkbox, Button, IconButton, Tooltip, Rating, Backdrop } from "@mui/material";
 import SeatCard from "./components/SeatCard";
@@ -13,6 +13,12 @@ import { Dna } from "react-loader-spinner";
 
 import type { Student } from "./types/Student";
 import type { ExecutionResult } from "./types/ExecutionResult";
+import type { Project } from "./types/Project";
+import type { LayoutDiff } from "./types/LayoutDiff";
+import type { Explanation } from "./types/Explanation";
+import type { SwapScore } from "./types/SwapScore";
+import type { TestVersions } from "./types/TestVersions";
+import type { ResizeError } from "./types/ResizeError";
 import SizeConfigDialog from "./components/SizeConfigDialog";
 
 function EditLayout() {
@@ -44,6 +50,14 @@ function EditLayout() {
 
   const [sizeConfigIsOpen, setSizeConfigIsOpen] = useState(false);
   const [resultIsOpen, setResultIsOpen] = useState(false);
+  const [diff, setDiff] = useState<LayoutDiff | null>(null);
+  const [explanation, setExplanation] = useState<Explanation | null>(null);
+  const [dragged, setDragged] = useState<[number, number] | null>(null);
+  const [fixedSeats, setFixedSeats] = useState<[number, number][]>([]);
+  const [swapScores, setSwapScores] = useState<Map<string, SwapScore>>(new Map());
+  const [examVersions, setExamVersions] = useState<TestVersions>("Checkerboard");
+  // test versions printed on the PDF, null unless the result is an exam layout
+  const [resultVersions, setResultVersions] = useState<TestVersions | null>(null);
 
   const [seats, setSeats] = useState<(Student | null)[][]>(() => {
     const seats = [];
@@ -70,6 +84,24 @@ function EditLayout() {
   });
 
   async function changeSize(newWidth: number, newDepth: number) {
+    // keep students on their seats so that the solver still knows their neighbours
+    try {
+      const resized = await invoke("resize_layout", { seatAssignment: seats, width: newWidth, depth: newDepth }) as (Student | null)[][];
+      setWidth(newWidth);
+      setDepth(newDepth);
+      setSeats(resized);
+      setFixedSeats([]);
+      setSizeConfigIsOpen(false);
+      return;
+    } catch (err) {
+      const error = err as ResizeError;
+      if (error.kind !== "TooFewSeats") {
+        await message(error.kind === "Other" ? error.message : String(err), { title: "エラー", type: "error" });
+        return;
+      }
+      // the new size has fewer seats than students
+    }
+
     const compressSeats = (seats: (Student | null)[][]) => {
       const rowCompressed = seats.filter((row) => row.some((student) => student !== null));
       if (rowCompressed.length === 0) {
@@ -116,6 +148,7 @@ function EditLayout() {
         }
       }
       setSeats(seats);
+      setFixedSeats([]);
       setSizeConfigIsOpen(false);
     }
   }
@@ -129,7 +162,12 @@ function EditLayout() {
     listen("save", async (_) => {
       const path = await save({ defaultPath: "seats.json", filters: [{ name: "JSON", extensions: ["json"] }] });
       if (path) {
-        writeTextFile(path, JSON.stringify(seats));
+        // the edited layout replaces the current layout of the project
+        try {
+          await invoke("save_project", { path, seatAssignment: seats });
+        } catch (err) {
+          await message("ファイルの保存に失敗しました。", { title: "エラー", type: "error" });
+        }
       }
     });
 
@@ -137,8 +175,12 @@ function EditLayout() {
       const path = await open({ filters: [{ name: "JSON", extensions: ["json"] }] });
       if (path) {
         try {
-          const seats = JSON.parse(await readTextFile(String(path))) as (Student | null)[][];
+          const project = await invoke("load_project", { path: String(path) }) as Project;
+          const seats = project.history.length > 0
+            ? project.history[project.history.length - 1].layout
+            : project.room.map((row) => row.map(() => null));
           setSeats(seats);
+          setFixedSeats([]);
           setWidth(seats[0].length);
           setDepth(seats.length);
         } catch (err) {
@@ -169,11 +211,31 @@ function EditLayout() {
   }
 
   function solve() {
+    runSolver("solve", { currentSeatAssignment: seats });
+  }
+
+  // re-optimises the layout around the students moved by hand
+  function repair() {
+    runSolver("repair_layout", { seatAssignment: seats, fixed: fixedSeats });
+  }
+
+  // seats students away from their usual neighbours and hands out test versions
+  function solveExam() {
+    runSolver("solve_exam", { currentSeatAssignment: seats, versions: examVersions }, examVersions);
+  }
+
+  function runSolver(command: string, args: Record<string, unknown>, versions: TestVersions | null = null) {
     setBackdropIsOpen(true);
-    invoke("solve", { currentSeatAssignment: seats })
+    invoke(command, args)
       .then((res) => {
         const executionResult = res as ExecutionResult;
         setResults(executionResult.new_seat_assignment);
+        setResultVersions(versions);
+        return invoke("diff_layouts", { previousSeatAssignment: seats, newSeatAssignment: executionResult.new_seat_assignment });
+      })
+      .then((res) => {
+        setDiff(res as LayoutDiff);
+        setExplanation(null);
         setResultIsOpen(true);
       })
       .catch((err) => {
@@ -192,7 +254,7 @@ function EditLayout() {
   }
 
   async function savePdf() {
-    const bytes = Array.from(await invoke("gen_pdf_bytes", { seatAssignment: result }) as number[]);
+    const bytes = Array.from(await invoke("gen_pdf_bytes", { seatAssignment: result, versions: resultVersions }) as number[]);
 
     const path = await save({ defaultPath: "result.pdf", filters: [{ name: "PDF", extensions: ["pdf"] }] });
     if (path) {
@@ -209,6 +271,96 @@ function EditLayout() {
     }
   }
 
+  async function explainPlacement(student: Student) {
+    try {
+      const res = await invoke("explain_placement", { currentSeatAssignment: seats, newSeatAssignment: result, studentId: student.id });
+      setExplanation(res as Explanation);
+    } catch (err) {
+      await message(String(err), { title: "エラー", type: "error" });
+    }
+  }
+
+  async function recordResult() {
+    try {
+      await invoke("record_history", { label: new Date().toLocaleDateString(), currentSeatAssignment: seats, newSeatAssignment: result });
+      setSeats(result);
+      setFixedSeats([]);
+      setWidth(result[0].length);
+      setDepth(result.length);
+      setResultIsOpen(false);
+    } catch (err) {
+      await message(String(err), { title: "エラー", type: "error" });
+    }
+  }
+
+  async function exportLayout(format: "Csv" | "Json") {
+    const extension = format.toLowerCase();
+    try {
+      const text = await invoke("export_layout", { previousSeatAssignment: seats, newSeatAssignment: result, format }) as string;
+
+      const path = await save({ defaultPath: `seats.${extension}`, filters: [{ name: format.toUpperCase(), extensions: [extension] }] });
+      if (path) {
+        writeTextFile(path, text);
+      }
+    } catch (err) {
+      await message(String(err), { title: "エラー", type: "error" });
+    }
+  }
+
+  async function exportZoneReport() {
+    try {
+      const text = await invoke("export_zone_report", { currentSeatAssignment: seats, newSeatAssignment: result, format: "Csv" }) as string;
+
+      const path = await save({ defaultPath: "zones.csv", filters: [{ name: "CSV", extensions: ["csv"] }] });
+      if (path) {
+        writeTextFile(path, text);
+      }
+    } catch (err) {
+      await message(String(err), { title: "エラー", type: "error" });
+    }
+  }
+
+  // scores every swap of the dragged student so that better and worse seats can be coloured
+  async function startDrag(x: number, y: number) {
+    setDragged([x, y]);
+    try {
+      const scores = new Map<string, SwapScore>();
+      await Promise.all(seats.flatMap((row, y2) => row.map(async (_, x2) => {
+        if (x2 !== x || y2 !== y) {
+          const score = await invoke("score_swap", { seatAssignment: seats, a: [x, y], b: [x2, y2] }) as SwapScore;
+          scores.set(`${x2},${y2}`, score);
+        }
+      })));
+      setSwapScores(scores);
+    } catch (err) {
+      setSwapScores(new Map());
+    }
+  }
+
+  function endDrag() {
+    setDragged(null);
+    setSwapScores(new Map());
+  }
+
+  function dropOn(x: number, y: number) {
+    if (dragged !== null) {
+      const newSeats = seats.map((row) => [...row]);
+      const [x1, y1] = dragged;
+      [newSeats[y1][x1], newSeats[y][x]] = [newSeats[y][x], newSeats[y1][x1]];
+      setSeats(newSeats);
+      setFixedSeats([...fixedSeats.filter(([x2, y2]) => !(x2 === x && y2 === y) && !(x2 === x1 && y2 === y1)), [x1, y1], [x, y]]);
+    }
+    endDrag();
+  }
+
+  function highlight(x: number, y: number) {
+    const score = swapScores.get(`${x},${y}`);
+    if (score === undefined || score.delta === 0) {
+      return null;
+    }
+    return score.delta > 0 && score.satisfies_constraints ? "better" : "worse";
+  }
+
   const Seats = (props: { width: number, depth: number, seats: (Student | null)[][] }) => {
     const elements = [];
     for (let i = 0; i < props.width * props.depth; i++) {
@@ -228,6 +380,10 @@ function EditLayout() {
 
               toggleDrawer();
             }}
+            highlight={highlight(x, y)}
+            onDragStart={() => startDrag(x, y)}
+            onDragEnd={endDrag}
+            onDrop={() => dropOn(x, y)}
           />
         </Grid>
       );
@@ -244,6 +400,20 @@ function EditLayout() {
       <Stack spacing={2}>
         <Seats width={width} depth={depth} seats={seats} />
         <Button fullWidth variant="contained" onClick={solve}>席替え実行</Button>
+        <Stack direction="row" spacing={2}>
+          <Select size="small" value={examVersions} onChange={(e) => setExamVersions(e.target.value as TestVersions)}>
+            <MenuItem value="Single">問題は1種類</MenuItem>
+            <MenuItem value="Checkerboard">A/B問題を市松模様に配布</MenuItem>
+            <MenuItem value="AttendanceOrder">A/B問題を出席番号順に配布</MenuItem>
+          </Select>
+          <Button fullWidth variant="outlined" onClick={solveExam}>試験用の座席を作成</Button>
+        </Stack>
+        {fixedSeats.length > 0 && (
+          <Stack direction="row" spacing={2}>
+            <Button fullWidth variant="outlined" onClick={repair}>動かした席を固定して再調整</Button>
+            <Button variant="text" onClick={() => setFixedSeats([])}>固定を解除</Button>
+          </Stack>
+        )}
       </Stack>
 
       <Drawer
@@ -394,6 +564,9 @@ function EditLayout() {
       />
       <ResultDialog
         seats={result}
+        diff={diff}
+        explanation={explanation}
+        onSeatClick={explainPlacement}
         open={resultIsOpen}
         onCloseClick={() => {
           setResultIsOpen(false);
@@ -401,6 +574,9 @@ function EditLayout() {
         onSave={saveResult}
         onPdfSave={savePdf}
         onCsvSave={saveCsv}
+        onExport={exportLayout}
+        onZoneReport={exportZoneReport}
+        onRecord={recordResult}
       />
     </Box>
   );
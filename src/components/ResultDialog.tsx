@@ -1,9 +1,18 @@
-import { Box, Stack, Grid, Dialog, DialogContent, DialogTitle, DialogActions, Button, Menu, MenuItem } from "@mui/material"
+import { Box, Stack, Grid, Dialog, DialogContent, DialogTitle, DialogActions, Button, Menu, MenuItem, Typography } from "@mui/material"
 import { Student } from "../types/Student";
+import { LayoutDiff } from "../types/LayoutDiff";
+import { Explanation, Zone } from "../types/Explanation";
 import SeatCard from "./SeatCard";
 import React from "react";
 
-function ResultDialog(props: { seats: (Student | null)[][], open: boolean, onClose?: () => void, onCloseClick?: () => void, onSave?: () => void, onPdfSave?: () => void, onCsvSave?: () => void }) {
+const zoneNames: Record<Zone, string> = {
+  Front: "前方",
+  Back: "後方",
+  Window: "窓側",
+  Aisle: "廊下側",
+};
+
+function ResultDialog(props: { seats: (Student | null)[][], diff?: LayoutDiff | null, explanation?: Explanation | null, onSeatClick?: (student: Student) => void, open: boolean, onClose?: () => void, onCloseClick?: () => void, onSave?: () => void, onPdfSave?: () => void, onCsvSave?: () => void, onExport?: (format: "Csv" | "Json") => void, onZoneReport?: () => void, onRecord?: () => void }) {
 
   const width = props.seats[0].length;
   const depth = props.seats.length;
@@ -34,6 +43,12 @@ function ResultDialog(props: { seats: (Student | null)[][], open: boolean, onClo
                     <Grid item xs={1}>
                       <SeatCard
                         student={props.seats[y][x]}
+                        onClick={() => {
+                          const student = props.seats[y][x];
+                          if (student !== null) {
+                            props.onSeatClick?.(student);
+                          }
+                        }}
                       />
                     </Grid>
                   );
@@ -41,11 +56,53 @@ function ResultDialog(props: { seats: (Student | null)[][], open: boolean, onClo
                 return elements;
               })()}
             </Grid>
+            {props.diff && (
+              <Stack spacing={1}>
+                <Typography>
+                  移動: {props.diff.summary.moved}人 / そのまま: {props.diff.summary.stayed}人 / 平均移動距離: {props.diff.summary.mean_distance.toFixed(1)} / 最大移動距離: {props.diff.summary.max_distance}
+                </Typography>
+                {props.diff.same_neighbours.length > 0 && (
+                  <Typography color="warning.main">
+                    周りが変わらない生徒: {props.diff.moves.filter((move) => props.diff!.same_neighbours.includes(move.id)).map((move) => move.name).join("、")}
+                  </Typography>
+                )}
+              </Stack>
+            )}
+            {props.explanation && (
+              <Stack spacing={1}>
+                <Typography variant="h6">
+                  {props.explanation.name} (前から{props.explanation.row}番目 左から{props.explanation.column}番目)
+                </Typography>
+                <Typography>
+                  前回の隣の生徒: {props.explanation.previous_neighbours.map((neighbour) => `${neighbour.name} (距離 ${neighbour.distance})`).join("、") || "なし"} / スコア: {props.explanation.previous_neighbour_score}
+                </Typography>
+                <Typography>
+                  黒板からの距離: {props.explanation.blackboard_distance.toFixed(1)} / 支援のスコア: {props.explanation.assistance_score}
+                </Typography>
+                <Typography>
+                  席のゾーン: {props.explanation.zones.map((zone) => zoneNames[zone]).join("、") || "なし"} / ローテーションのスコア: {props.explanation.rotation_score}
+                </Typography>
+                <Typography>
+                  周りのバランスへの寄与: {props.explanation.balance_contribution}
+                </Typography>
+                {props.explanation.constraints.length > 0 && (
+                  <Typography>
+                    制約: {props.explanation.constraints.filter((status) => status.satisfied).length} / {props.explanation.constraints.length} 件を満たしています
+                  </Typography>
+                )}
+                {props.explanation.swaps.length > 0 && (
+                  <Typography>
+                    最も良い入れ替え: 前から{props.explanation.swaps[0].row}番目 左から{props.explanation.swaps[0].column}番目 (スコア {props.explanation.swaps[0].score_change >= 0 ? "+" : ""}{props.explanation.swaps[0].score_change})
+                  </Typography>
+                )}
+              </Stack>
+            )}
           </Stack>
         </Box>
       </DialogContent>
       <DialogActions>
         <Button onClick={props.onCloseClick} sx={{ boxShadow: 0 }}>閉じる</Button>
+        <Button onClick={props.onRecord} sx={{ boxShadow: 0 }}>採用して履歴に記録</Button>
         <Button onClick={(event: React.MouseEvent<HTMLButtonElement>) => setMenuAnchorEl(event.currentTarget)} sx={{ boxShadow: 0 }}>保存</Button>
         <Menu
           id="file-menu"
@@ -58,6 +115,9 @@ function ResultDialog(props: { seats: (Student | null)[][], open: boolean, onClo
         >
           <MenuItem onClick={props.onSave}>作業ファイル</MenuItem>
           <MenuItem onClick={props.onCsvSave}>CSV</MenuItem>
+          <MenuItem onClick={() => props.onExport?.("Csv")}>生徒一覧 (CSV)</MenuItem>
+          <MenuItem onClick={() => props.onExport?.("Json")}>生徒一覧 (JSON)</MenuItem>
+          <MenuItem onClick={props.onZoneReport}>ゾーン集計 (CSV)</MenuItem>
           <MenuItem onClick={props.onPdfSave}>PDF</MenuItem>
         </Menu>
       </DialogActions>
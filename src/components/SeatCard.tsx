@@ -1,8 +1,23 @@
 import { Card, CardActionArea, CardContent, Grid, Tooltip, Typography } from "@mui/material";
 
+import React from "react";
+
 import type { Student } from "../types/Student";
 
-function SeatCard(props: { student: Student | null, onClick?: () => void }) {
+function SeatCard(props: { student: Student | null, onClick?: () => void, highlight?: "better" | "worse" | null, onDragStart?: () => void, onDragEnd?: () => void, onDrop?: () => void }) {
+  const dragProps = {
+    draggable: props.student !== null && props.onDragStart !== undefined,
+    onDragStart: props.onDragStart,
+    onDragEnd: props.onDragEnd,
+    onDragOver: (event: React.DragEvent) => {
+      if (props.onDrop) {
+        event.preventDefault();
+      }
+    },
+    onDrop: props.onDrop,
+    sx: props.highlight ? { borderColor: props.highlight === "better" ? "success.main" : "error.main", borderWidth: 2 } : undefined,
+  };
+
   const studentInfo = (student: Student) => {
     let res = student.gender == "Male" ? "男" : "女";
     res += ` 学力: ${student.academic_ability} 運動能力: ${student.exercise_ability} リーダーシップ: ${student.leadership_ability}`
@@ -14,7 +29,7 @@ function SeatCard(props: { student: Student | null, onClick?: () => void }) {
 
   if (props.student === null) {
     return (
-      <Card variant="outlined">
+      <Card variant="outlined" {...dragProps}>
           <CardActionArea onClick={props.onClick}>
             <CardContent sx={{ display: 'flex', justifyContent: 'center', alignItems: 'center' }}>
               <Typography>
@@ -27,7 +42,7 @@ function SeatCard(props: { student: Student | null, onClick?: () => void }) {
   } else {
     return (
       <Tooltip title={studentInfo(props.student)} arrow>
-        <Card variant="outlined">
+        <Card variant="outlined" {...dragProps}>
           <CardActionArea onClick={props.onClick}>
             <CardContent sx={{ display: 'flex', justifyContent: 'center', alignItems: 'center' }}>
               <Typography>
@@ -0,0 +1,32 @@
+export type PreviousNeighbour = {
+    id: number;
+    name: string;
+    layouts: number;
+    distance: number;
+}
+
+export type SwapOption = {
+    row: number;
+    column: number;
+    student: number | null;
+    score_change: number;
+    satisfies_constraints: boolean;
+}
+
+export type Zone = "Front" | "Back" | "Window" | "Aisle";
+
+export type Explanation = {
+    id: number;
+    name: string;
+    row: number;
+    column: number;
+    previous_neighbours: PreviousNeighbour[];
+    previous_neighbour_score: number;
+    blackboard_distance: number;
+    assistance_score: number;
+    zones: Zone[];
+    rotation_score: number;
+    balance_contribution: number;
+    constraints: { constraint: unknown, satisfied: boolean }[];
+    swaps: SwapOption[];
+}
@@ -0,0 +1,22 @@
+export type StudentMove = {
+    id: number;
+    name: string;
+    from: [number, number] | null;
+    to: [number, number] | null;
+    distance: number | null;
+}
+
+export type DiffSummary = {
+    moved: number;
+    stayed: number;
+    joined: number;
+    left: number;
+    mean_distance: number;
+    max_distance: number;
+}
+
+export type LayoutDiff = {
+    moves: StudentMove[];
+    same_neighbours: number[];
+    summary: DiffSummary;
+}
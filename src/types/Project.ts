@@ -0,0 +1,15 @@
+import { Student } from "./Student";
+
+export type HistoryEntry = {
+    timestamp: number;
+    label: string;
+    layout: (Student | null)[][];
+}
+
+export type Project = {
+    version: number;
+    room: boolean[][];
+    roster: Student[];
+    history: HistoryEntry[];
+    settings: unknown;
+}
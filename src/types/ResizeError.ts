@@ -0,0 +1 @@
+export type ResizeError = { kind: "TooFewSeats" } | { kind: "Other"; message: string };
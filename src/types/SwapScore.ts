@@ -0,0 +1,17 @@
+export type ScoreComponents = {
+    individual: number;
+    academic: number;
+    exercise: number;
+    leadership: number;
+    gender: number;
+}
+
+export type SwapScore = {
+    score: number;
+    swapped_score: number;
+    delta: number;
+    before: ScoreComponents;
+    after: ScoreComponents;
+    changed: ("Individual" | "Academic" | "Exercise" | "Leadership" | "Gender")[];
+    satisfies_constraints: boolean;
+}
@@ -0,0 +1 @@
+export type TestVersions = "Single" | "Checkerboard" | "AttendanceOrder";
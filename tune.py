@@ -1,21 +1,22 @@
-import statistics
-import os
-import joblib
+import json
 import time
 import optuna
 import subprocess
 
+# 事前に `cargo build --release --manifest-path cli/Cargo.toml` でビルドしておく
+TUNE_BIN = "./cli/target/release/tune"
+
 def calc_score_and_sigma(t0,t1):
-    res = subprocess.check_output(f"./target/release/tmp {t0} {t1}",shell=True)
-    # コンマ区切り（score, sigma）で返ってくるので分割
-    score, sigma = res.decode().split(",")
-    return float(score), float(sigma)
+    res = subprocess.check_output(f"{TUNE_BIN} --temperature1 {t0} --temperature2 {t1} --format json",shell=True)
+    # JSON（mean, sigma, individual_mean, runtime_ms など）で返ってくる
+    stats = json.loads(res.decode())
+    return stats["mean"], stats["sigma"]
 
 def objective(trial: optuna.trial.Trial):
     start = time.time()
 
-    t1 = trial.suggest_float("t1", 0, 200)
-    t2 = trial.suggest_float("t2", 0, 10)
+    t1 = trial.suggest_float("t1", 1e-3, 200)
+    t2 = trial.suggest_float("t2", 1e-3, 10)
 
     mean,sigma = calc_score_and_sigma(t1,t2)
     print(f"elapsed: {time.time() - start}")